@@ -256,7 +256,7 @@ export async function POST(request: NextRequest) {
                 // MODE 1: ASHBORN ONLY (Merkle Tree Commitment)
                 if (mode === 'ashborn') {
                     try {
-                        const { Ashborn } = await import('@alleyboss/ashborn-sdk');
+                        const { Ashborn, DEMO_PROGRAM_ID } = await import('@alleyboss/ashborn-sdk');
 
                         // Simple Wallet Adapter to avoid Anchor version issues
                         const wallet = {
@@ -266,7 +266,8 @@ export async function POST(request: NextRequest) {
                             payer: relayKeypair
                         };
 
-                        const ashborn = new Ashborn(connection, wallet as any);
+                        // No shield circuit prover on the server: use the demo build
+                        const ashborn = new Ashborn(connection, wallet as any, { programId: DEMO_PROGRAM_ID });
 
                         // Debug: Log the pubkey and vault being used
                         console.log('[Ashborn] Using wallet pubkey:', relayKeypair.publicKey.toBase58());
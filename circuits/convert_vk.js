@@ -85,9 +85,8 @@ function convertVK(vkPath, circuitName) {
 const args = process.argv.slice(2);
 if (args.length === 0) {
     // Convert all VKs
-    const vks = ['shield', 'transfer', 'range'];
+    const vks = ['shield', 'transfer', 'range', 'ownership'];
     let output = `//! Real Groth16 Verification Keys\n//!\n//! Generated from Circom circuits using snarkjs\n//! DO NOT EDIT - regenerate using convert_vk.js\n\n`;
-    output += `pub const DEMO_MODE: bool = false; // Real VKs!\n\n`;
 
     for (const name of vks) {
         const vkPath = `build/${name}_vkey.json`;
@@ -0,0 +1,28 @@
+pragma circom 2.1.6;
+
+include "node_modules/circomlib/circuits/poseidon.circom";
+
+/**
+ * OwnershipProof - Proves ownership without revealing balance
+ *
+ * ownerCommitment is the vault's on-chain view_key_hash; vaultAddress is the
+ * vault PDA with its most significant byte cleared (fits the BN254 field).
+ */
+template OwnershipProof() {
+    // Private inputs
+    signal input nullifierSecret;
+    signal input viewKey;
+    
+    // Public inputs
+    signal input ownerCommitment; // Public commitment to owner identity
+    signal input vaultAddress;    // The vault being proven
+    
+    // Prove knowledge of nullifier secret
+    component ownerHash = Poseidon(3);
+    ownerHash.inputs[0] <== nullifierSecret;
+    ownerHash.inputs[1] <== viewKey;
+    ownerHash.inputs[2] <== vaultAddress;
+    ownerHash.out === ownerCommitment;
+}
+
+component main {public [ownerCommitment, vaultAddress]} = OwnershipProof();
@@ -40,24 +40,4 @@ template RangeProof() {
     leMax.out === 1;
 }
 
-/**
- * OwnershipProof - Proves ownership without revealing balance
- */
-template OwnershipProof() {
-    // Private inputs
-    signal input nullifierSecret;
-    signal input viewKey;
-    
-    // Public inputs
-    signal input ownerCommitment; // Public commitment to owner identity
-    signal input vaultAddress;    // The vault being proven
-    
-    // Prove knowledge of nullifier secret
-    component ownerHash = Poseidon(3);
-    ownerHash.inputs[0] <== nullifierSecret;
-    ownerHash.inputs[1] <== viewKey;
-    ownerHash.inputs[2] <== vaultAddress;
-    ownerHash.out === ownerCommitment;
-}
-
 component main {public [commitment, minValue, maxValue]} = RangeProof();
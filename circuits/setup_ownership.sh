@@ -0,0 +1,97 @@
+#!/bin/bash
+
+# Ownership circuit setup
+#
+# Rebuilds every ownership artifact from ownership.circom:
+#   build/ownership.r1cs, build/ownership_js/, build/ownership.sym
+#   build/ownership_0000.zkey  (Groth16 phase-2 key, no contributions)
+#   build/ownership_vkey.json
+#   build/ownership.sha256     (hashes of the r1cs, zkey and vkey)
+#   ../programs/ashborn/src/zk/vkeys_generated.rs  (via convert_vk.js)
+#
+# Commit all of them together. No ownership key is committed until this
+# script has been run with the pinned tools; one derived any other way
+# can't be checked against the public phase-1 parameters.
+#
+# The output is deterministic: circom is pinned below, circomlib and snarkjs
+# by package-lock.json, the phase-1 file is the public Hermez ceremony, and
+# an uncontributed _0000 key depends only on the r1cs and the ptau. Run it
+# twice and the keys are byte-identical.
+#
+# Like the other *_0000 keys this is a single-party development setup; run a
+# phase-2 ceremony (snarkjs zkey contribute / beacon) before mainnet and
+# upload that key with create_verifying_key instead.
+#
+# Usage: ./setup_ownership.sh [--check]
+#   --check  rebuild into a temp dir and fail if the hashes in
+#            build/ownership.sha256 differ
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+# Compiler optimizations change the r1cs, so the compiler version is part of the key
+CIRCOM_VERSION="2.1.6"
+
+# Poseidon(3) needs far fewer than 2^12 constraints
+PTAU="${PTAU:-pot12_final.ptau}"
+PTAU_URL="https://storage.googleapis.com/zkevm/ptau/powersOfTau28_hez_final_12.ptau"
+
+CHECK=0
+if [ "${1:-}" = "--check" ]; then
+    CHECK=1
+fi
+
+if ! command -v circom &> /dev/null; then
+    echo "❌ circom not found. Install circom $CIRCOM_VERSION from https://docs.circom.io/getting-started/installation/"
+    exit 1
+fi
+if ! circom --version | grep -q "$CIRCOM_VERSION"; then
+    echo "❌ circom $CIRCOM_VERSION required, found: $(circom --version)"
+    exit 1
+fi
+
+echo "📦 Installing pinned circomlib and snarkjs..."
+npm ci --silent
+SNARKJS="npx --no-install snarkjs"
+
+if [ ! -f "$PTAU" ]; then
+    echo "⬇️  Downloading phase-1 parameters..."
+    curl -fL -o "$PTAU" "$PTAU_URL"
+fi
+echo "🔍 Verifying phase-1 parameters..."
+$SNARKJS powersoftau verify "$PTAU"
+
+OUT=build
+if [ "$CHECK" = "1" ]; then
+    OUT=$(mktemp -d)
+    trap 'rm -rf "$OUT"' EXIT
+fi
+mkdir -p "$OUT"
+
+echo "🔨 Compiling ownership.circom..."
+circom ownership.circom --r1cs --wasm --sym -o "$OUT"
+
+echo "🔑 Groth16 setup..."
+$SNARKJS groth16 setup "$OUT/ownership.r1cs" "$PTAU" "$OUT/ownership_0000.zkey"
+$SNARKJS zkey export verificationkey "$OUT/ownership_0000.zkey" "$OUT/ownership_vkey.json"
+(cd "$OUT" && sha256sum ownership.r1cs ownership_0000.zkey ownership_vkey.json) > "$OUT/ownership.sha256"
+
+if [ "$CHECK" = "1" ]; then
+    if [ ! -f build/ownership.sha256 ]; then
+        echo "❌ build/ownership.sha256 is missing; run without --check and commit build/"
+        exit 1
+    fi
+    if ! diff -q "$OUT/ownership.sha256" build/ownership.sha256 > /dev/null; then
+        echo "❌ build/ownership.sha256 does not match ownership.circom; rerun without --check"
+        exit 1
+    fi
+    echo "✅ build/ownership.sha256 matches ownership.circom"
+    exit 0
+fi
+
+echo "🦀 Regenerating Rust verifying keys..."
+node convert_vk.js
+
+echo ""
+echo "✅ Ownership artifacts written to build/"
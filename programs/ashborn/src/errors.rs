@@ -40,6 +40,9 @@ pub enum AshbornError {
     #[msg("Deposit amount doesn't match commitment")]
     CommitmentMismatch,
     
+    #[msg("Requested amount doesn't match the note's value")]
+    NoteValueMismatch,
+    
     // ============ Transfer Errors (6200-6299) ============
     
     #[msg("Nullifier has already been used (double-spend attempt)")]
@@ -74,6 +77,9 @@ pub enum AshbornError {
     #[msg("Compliance proof already exists")]
     ProofAlreadyExists,
     
+    #[msg("Vault has no view key hash to prove ownership against")]
+    ViewKeyNotSet,
+    
     // ============ Protocol Errors (6400-6499) ============
     
     #[msg("Protocol is currently paused")]
@@ -97,7 +103,7 @@ pub enum AshbornError {
     #[msg("Custom proof verification failed")]
     CustomProofFailed,
 
-    #[msg("Too soon to unshield (24h lock)")]
+    #[msg("Too soon to unshield (privacy delay not elapsed)")]
     TooSoonToUnshield,
 
     #[msg("Invalid withdraw proof")]
@@ -112,6 +118,75 @@ pub enum AshbornError {
     #[msg("Invalid fee recipient")]
     InvalidFeeRecipient,
     
+    #[msg("Privacy delay cannot be negative")]
+    InvalidPrivacyDelay,
+    
+    #[msg("No admin handover is pending for this signer")]
+    NoPendingAdmin,
+    
+    #[msg("Only the admin or guardian can pause the protocol")]
+    InvalidPauseAuthority,
+    
+    #[msg("Change ETA is earlier than the minimum timelock")]
+    TimelockTooShort,
+    
+    #[msg("Timelock has not elapsed for this change")]
+    TimelockNotElapsed,
+    
+    #[msg("Queued change has passed its grace period")]
+    ChangeExpired,
+    
+    #[msg("Rate limit values must be greater than zero")]
+    InvalidRateLimitConfig,
+    
+    #[msg("Withdrawals halted by the outflow circuit breaker")]
+    WithdrawalsHalted,
+    
+    #[msg("Withdrawal would exceed the pool's outflow limit for this epoch")]
+    OutflowLimitExceeded,
+    
+    #[msg("Outflow limit values must be greater than zero")]
+    InvalidOutflowLimitConfig,
+    
+    #[msg("Deposit would exceed the pool's TVL cap")]
+    PoolCapExceeded,
+    
+    #[msg("Pool balance does not cover outstanding shielded value")]
+    PoolInsolvent,
+    
+    #[msg("Account uses an old layout; call the matching migrate instruction")]
+    AccountNotMigrated,
+    
+    #[msg("Account is already on the current layout")]
+    AccountAlreadyMigrated,
+    
+    #[msg("Account data does not match any known layout")]
+    InvalidAccountLayout,
+    
+    #[msg("Root history length out of range")]
+    InvalidRootHistoryLength,
+    
+    #[msg("Merkle tree is full")]
+    TreeFull,
+    
+    #[msg("Commitment tree is not the active shard")]
+    InactiveCommitmentTree,
+    
+    #[msg("Nullifier set shard is full, grow it first")]
+    NullifierSetFull,
+    
+    #[msg("Batch is empty, too large, or its inputs don't line up")]
+    InvalidBatchSize,
+    
+    #[msg("Outflow circuit breaker is not tripped")]
+    OutflowNotTripped,
+    
+    #[msg("Nullifier set shard still has free slots")]
+    NullifierSetNotFull,
+    
+    #[msg("Nullifier set shard is at its maximum size")]
+    NullifierSetAtMaxSize,
+    
     // ============ Crypto Errors (6500-6599) ============
     
     #[msg("Hash computation failed")]
@@ -129,6 +204,18 @@ pub enum AshbornError {
     #[msg("Invalid public key format")]
     InvalidPublicKey,
     
+    #[msg("Public input is not a canonical BN254 field element")]
+    NonCanonicalFieldElement,
+    
+    #[msg("Verifying key is for another circuit, incomplete, or malformed")]
+    InvalidVerifyingKey,
+    
+    #[msg("Verifying key is not the active key for its circuit")]
+    InactiveVerifyingKey,
+    
+    #[msg("Verifying key can no longer be modified")]
+    VerifyingKeyLocked,
+    
     // ============ Integration Errors (6600-6699) ============
     
     #[msg("ShadowWire integration error")]
@@ -0,0 +1,137 @@
+//! Anchor events for off-chain indexers and wallets
+//!
+//! Typed, parseable counterparts to the `msg!` logs.
+
+use anchor_lang::prelude::*;
+
+use crate::state::{CircuitId, ParameterChange};
+use crate::ProofType;
+
+// ============================================================================
+// Emitted through `emit_cpi!`: the event lands in the instruction data of a
+// self-CPI, so it survives log truncation and can be read back from
+// transaction history. Replaying `CommitmentInserted` in order rebuilds
+// every commitment tree shard.
+// ============================================================================
+
+/// A configuration change entered the timelock
+#[event]
+pub struct ChangeQueued {
+    pub id: u64,
+    pub change: ParameterChange,
+    pub proposer: Pubkey,
+    pub eta: i64,
+}
+
+/// A queued change was applied to the protocol
+#[event]
+pub struct ChangeExecuted {
+    pub id: u64,
+    pub change: ParameterChange,
+    pub executed_at: i64,
+}
+
+/// A queued change was cancelled by the admin
+#[event]
+pub struct ChangeCancelled {
+    pub id: u64,
+    pub change: ParameterChange,
+}
+
+/// A leaf was appended to a commitment tree shard
+#[event]
+pub struct CommitmentInserted {
+    /// Shard the leaf went into
+    pub tree: Pubkey,
+    /// Global leaf index (see `CommitmentTree::global_index`)
+    pub index: u64,
+    pub leaf: [u8; 32],
+    /// Shard root after the instruction's appends
+    pub new_root: [u8; 32],
+}
+
+/// A nullifier was recorded as spent
+#[event]
+pub struct NullifierSpent {
+    pub nullifier: [u8; 32],
+    pub spent_at: i64,
+}
+
+/// A shielded note account was created
+#[event]
+pub struct NoteCreated {
+    pub note: Pubkey,
+    pub vault: Pubkey,
+    pub commitment: [u8; 32],
+    /// Global leaf index of the commitment
+    pub leaf_index: u64,
+    pub denomination_tier: u8,
+    pub unshield_after: i64,
+}
+
+/// A protocol, pool or circuit breaker setting changed
+#[event]
+pub struct ProtocolConfigChanged {
+    /// Key that made the change
+    pub authority: Pubkey,
+    pub change: ConfigChange,
+}
+
+/// What a `ProtocolConfigChanged` event changed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigChange {
+    FeeRecipient(Pubkey),
+    /// Handover proposed (`Pubkey::default()` cancels it)
+    AdminProposed(Pubkey),
+    AdminAccepted(Pubkey),
+    Guardian(Pubkey),
+    Paused(bool),
+    PoolCap {
+        mint: Pubkey,
+        max_tvl: u64,
+    },
+    OutflowLimit {
+        mint: Pubkey,
+        max_outflow_per_epoch: u64,
+        epoch_duration_slots: u64,
+    },
+    OutflowTripped {
+        mint: Pubkey,
+    },
+    OutflowReset {
+        mint: Pubkey,
+    },
+    VerifyingKeyRetired {
+        circuit: CircuitId,
+        version: u32,
+    },
+    RootHistoryGrown {
+        tree: Pubkey,
+        root_history_len: u32,
+    },
+    /// Uploaded but not usable until activated through the timelock
+    VerifyingKeyCreated {
+        circuit: CircuitId,
+        version: u32,
+    },
+}
+
+/// Accrued fees were moved out of a fee vault
+#[event]
+pub struct FeesWithdrawn {
+    pub mint: Pubkey,
+    /// Token account credited
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// A selective disclosure proof was verified and stored
+#[event]
+pub struct ComplianceProofStored {
+    pub proof_record: Pubkey,
+    pub vault: Pubkey,
+    pub proof_type: ProofType,
+    pub range_min: u64,
+    pub range_max: u64,
+    pub expires_at: i64,
+}
@@ -0,0 +1,158 @@
+//! Admin governance over ProtocolState
+//!
+//! Fee recipient and key rotation - admin key only. Fee, privacy delay and
+//! rate limit changes go through the timelock (see `timelock.rs`).
+//! The guardian is a hot key that can pull the pause switch and nothing else.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+use crate::events::{ConfigChange, ProtocolConfigChanged};
+use crate::state::ProtocolState;
+
+/// Accounts for admin-only protocol configuration
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AdminConfig<'info> {
+    /// Global protocol configuration
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for pausing the protocol (admin or guardian)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PauseProtocol<'info> {
+    /// Global protocol configuration
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.can_pause(&authority.key()) @ AshbornError::InvalidPauseAuthority,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// Admin or guardian
+    pub authority: Signer<'info>,
+}
+
+/// Accounts for completing an admin handover
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    /// Global protocol configuration
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.pending_admin == new_admin.key() @ AshbornError::NoPendingAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+
+    /// The proposed admin
+    pub new_admin: Signer<'info>,
+}
+
+/// Update the account that receives protocol fees
+pub fn set_fee_recipient_handler(ctx: Context<AdminConfig>, fee_recipient: Pubkey) -> Result<()> {
+    require!(fee_recipient != Pubkey::default(), AshbornError::InvalidFeeRecipient);
+
+    ctx.accounts.protocol_state.fee_recipient = fee_recipient;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::FeeRecipient(fee_recipient),
+    });
+
+    msg!("Fee recipient updated: {}", fee_recipient);
+
+    Ok(())
+}
+
+/// Propose a new admin; takes effect once they call `accept_admin`
+///
+/// Proposing `Pubkey::default()` cancels a pending handover.
+pub fn propose_admin_handler(ctx: Context<AdminConfig>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.protocol_state.pending_admin = new_admin;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::AdminProposed(new_admin),
+    });
+
+    msg!("Admin handover proposed: {}", new_admin);
+
+    Ok(())
+}
+
+/// Complete an admin handover (signed by the proposed admin)
+pub fn accept_admin_handler(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let protocol_state = &mut ctx.accounts.protocol_state;
+    let old_admin = protocol_state.admin;
+    protocol_state.admin = ctx.accounts.new_admin.key();
+    protocol_state.pending_admin = Pubkey::default();
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: protocol_state.admin,
+        change: ConfigChange::AdminAccepted(protocol_state.admin),
+    });
+
+    msg!("Admin handover complete: {} -> {}", old_admin, protocol_state.admin);
+
+    Ok(())
+}
+
+/// Replace the guardian key
+pub fn set_guardian_handler(ctx: Context<AdminConfig>, guardian: Pubkey) -> Result<()> {
+    require!(guardian != Pubkey::default(), AshbornError::InvalidPauseAuthority);
+
+    ctx.accounts.protocol_state.guardian = guardian;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::Guardian(guardian),
+    });
+
+    msg!("Guardian updated: {}", guardian);
+
+    Ok(())
+}
+
+/// Stop all state-changing operations
+pub fn pause_handler(ctx: Context<PauseProtocol>) -> Result<()> {
+    ctx.accounts.protocol_state.paused = true;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.authority.key(),
+        change: ConfigChange::Paused(true),
+    });
+
+    msg!("Protocol paused by: {}", ctx.accounts.authority.key());
+
+    Ok(())
+}
+
+/// Resume normal operations
+pub fn unpause_handler(ctx: Context<AdminConfig>) -> Result<()> {
+    ctx.accounts.protocol_state.paused = false;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::Paused(false),
+    });
+
+    msg!("Protocol unpaused by admin: {}", ctx.accounts.admin.key());
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Outflow circuit breaker management
+//!
+//! The admin configures a per-mint withdrawal cap; the admin or guardian
+//! trips the breaker to halt withdrawals and re-arms it afterwards.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::errors::AshbornError;
+use crate::events::{ConfigChange, ProtocolConfigChanged};
+use crate::state::{OutflowLimit, ProtocolState};
+
+/// Accounts for configuring a mint's outflow limit
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetOutflowLimit<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The mint being limited
+    pub mint: Account<'info, Mint>,
+
+    /// Outflow limit for this mint
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = OutflowLimit::SIZE,
+        seeds = [b"outflow_limit", mint.key().as_ref()],
+        bump,
+    )]
+    pub outflow_limit: Box<Account<'info, OutflowLimit>>,
+
+    /// Protocol admin (payer)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for tripping the breaker
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TripOutflowLimit<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.can_pause(&authority.key()) @ AshbornError::InvalidPauseAuthority,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Outflow limit being tripped
+    #[account(
+        mut,
+        seeds = [b"outflow_limit", outflow_limit.mint.as_ref()],
+        bump = outflow_limit.bump,
+        constraint = outflow_limit.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub outflow_limit: Box<Account<'info, OutflowLimit>>,
+
+    /// Admin or guardian
+    pub authority: Signer<'info>,
+}
+
+/// Accounts for re-arming a tripped breaker
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResetOutflowLimit<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.can_pause(&authority.key()) @ AshbornError::InvalidPauseAuthority,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Outflow limit being reset
+    #[account(
+        mut,
+        seeds = [b"outflow_limit", outflow_limit.mint.as_ref()],
+        bump = outflow_limit.bump,
+        constraint = outflow_limit.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub outflow_limit: Box<Account<'info, OutflowLimit>>,
+
+    /// Admin or guardian
+    pub authority: Signer<'info>,
+}
+
+/// Create or update the withdrawal cap for a mint
+pub fn set_handler(
+    ctx: Context<SetOutflowLimit>,
+    max_outflow_per_epoch: u64,
+    epoch_duration_slots: u64,
+) -> Result<()> {
+    let outflow_limit = &mut ctx.accounts.outflow_limit;
+
+    // init_if_needed leaves a new account zeroed; an existing one must be current
+    if outflow_limit.version == 0 {
+        outflow_limit.version = OutflowLimit::CURRENT_VERSION;
+    }
+    require!(outflow_limit.is_current(), AshbornError::AccountNotMigrated);
+
+    outflow_limit.update_config(max_outflow_per_epoch, epoch_duration_slots, Clock::get()?.slot)?;
+    outflow_limit.mint = ctx.accounts.mint.key();
+    outflow_limit.bump = ctx.bumps.outflow_limit;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::OutflowLimit {
+            mint: outflow_limit.mint,
+            max_outflow_per_epoch,
+            epoch_duration_slots,
+        },
+    });
+
+    msg!(
+        "Outflow limit for {}: {} per {} slots",
+        outflow_limit.mint,
+        max_outflow_per_epoch,
+        epoch_duration_slots
+    );
+
+    Ok(())
+}
+
+/// Halt withdrawals for a mint until the breaker is reset
+pub fn trip_handler(ctx: Context<TripOutflowLimit>) -> Result<()> {
+    let outflow_limit = &mut ctx.accounts.outflow_limit;
+    outflow_limit.trip(Clock::get()?.unix_timestamp);
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.authority.key(),
+        change: ConfigChange::OutflowTripped {
+            mint: outflow_limit.mint,
+        },
+    });
+
+    msg!(
+        "Outflow breaker for {} tripped by: {}",
+        outflow_limit.mint,
+        ctx.accounts.authority.key()
+    );
+
+    Ok(())
+}
+
+/// Re-arm a tripped breaker
+pub fn reset_handler(ctx: Context<ResetOutflowLimit>) -> Result<()> {
+    let outflow_limit = &mut ctx.accounts.outflow_limit;
+    outflow_limit.reset()?;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.authority.key(),
+        change: ConfigChange::OutflowReset {
+            mint: outflow_limit.mint,
+        },
+    });
+
+    msg!(
+        "Outflow breaker for {} reset by: {}",
+        outflow_limit.mint,
+        ctx.accounts.authority.key()
+    );
+
+    Ok(())
+}
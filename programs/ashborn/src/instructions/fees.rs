@@ -0,0 +1,88 @@
+//! Protocol fee withdrawal
+//!
+//! Unshield fees accumulate in a per-mint fee vault, never in the user pool.
+//! Only the admin can move them out, and only to the configured fee recipient.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::errors::AshbornError;
+use crate::events::FeesWithdrawn;
+use crate::state::ProtocolState;
+
+/// Accounts for withdrawing protocol fees
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Fee vault for this mint
+    #[account(
+        mut,
+        seeds = [b"fee_vault", fee_vault.mint.as_ref()],
+        bump,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Fee recipient's token account
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == protocol_state.fee_recipient @ AshbornError::InvalidFeeRecipient,
+        constraint = recipient_token_account.mint == fee_vault.mint @ AshbornError::InvalidMint,
+    )]
+    pub recipient_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Pool authority PDA for this mint (controls the fee vault)
+    /// CHECK: PDA authority for pool, verified by seeds
+    #[account(
+        seeds = [b"pool_authority", fee_vault.mint.as_ref()],
+        bump,
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Move accrued fees to the fee recipient
+pub fn withdraw_handler(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+    require!(amount > 0, AshbornError::ZeroAmount);
+    require!(
+        amount <= ctx.accounts.fee_vault.amount,
+        AshbornError::InsufficientBalance
+    );
+
+    let mint = ctx.accounts.fee_vault.mint;
+    let pool_seeds = &[b"pool_authority".as_ref(), mint.as_ref(), &[ctx.bumps.pool_authority]];
+    let signer_seeds = &[&pool_seeds[..]];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+            from: ctx.accounts.fee_vault.to_account_info(),
+            to: ctx.accounts.recipient_token_account.to_account_info(),
+            authority: ctx.accounts.pool_authority.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::transfer(transfer_ctx, amount)?;
+
+    emit_cpi!(FeesWithdrawn {
+        mint,
+        recipient: ctx.accounts.recipient_token_account.key(),
+        amount,
+    });
+
+    msg!("Fees withdrawn: {} to {}", amount, ctx.accounts.protocol_state.fee_recipient);
+
+    Ok(())
+}
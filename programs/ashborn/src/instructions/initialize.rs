@@ -4,7 +4,9 @@
 
 use anchor_lang::prelude::*;
 
+use crate::errors::AshbornError;
 use crate::state::ShadowVault;
+use crate::zk::to_field;
 
 /// Accounts for initializing a shadow vault
 #[derive(Accounts)]
@@ -27,6 +29,23 @@ pub struct InitializeVault<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for setting a vault's view key hash
+#[derive(Accounts)]
+pub struct SetViewKey<'info> {
+    /// The vault being updated
+    #[account(
+        mut,
+        seeds = [b"shadow_vault", owner.key().as_ref()],
+        bump = shadow_vault.bump,
+        has_one = owner @ AshbornError::Unauthorized,
+        constraint = shadow_vault.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub shadow_vault: Account<'info, ShadowVault>,
+
+    /// Vault owner
+    pub owner: Signer<'info>,
+}
+
 /// Initialize a new shadow vault for the user
 ///
 /// # Arguments
@@ -43,14 +62,32 @@ pub fn handler(ctx: Context<InitializeVault>) -> Result<()> {
     vault.owner = ctx.accounts.owner.key();
     vault.bump = ctx.bumps.shadow_vault;
     vault.note_count = 0;
-    vault.view_key_hash = [0u8; 32]; // User can set later
+    vault.view_key_hash = [0u8; 32]; // Set later via set_view_key_hash
     vault.encrypted_nullifier_secret = [0u8; 48];
     vault.encrypted_balance_hint = [0u8; 48]; // Privacy: balance computed locally
     vault.created_at = clock.unix_timestamp;
     vault.last_activity = clock.unix_timestamp;
-    vault._reserved = [0u8; 64];
+    vault.version = ShadowVault::CURRENT_VERSION;
+    vault._reserved = [0u8; 63];
 
     msg!("Shadow Vault initialized for: {}", vault.owner);
 
     Ok(())
 }
+
+/// Set the view key hash ownership proofs are checked against
+///
+/// The hash is `Poseidon(nullifierSecret, viewKey, vault)` as computed by
+/// `ownership.circom`, so it must be a canonical field element.
+pub fn set_view_key_handler(ctx: Context<SetViewKey>, view_key_hash: [u8; 32]) -> Result<()> {
+    require!(view_key_hash != [0u8; 32], AshbornError::ViewKeyNotSet);
+    let view_key_hash = to_field(&view_key_hash)?;
+
+    let vault = &mut ctx.accounts.shadow_vault;
+    vault.view_key_hash = view_key_hash;
+    vault.last_activity = Clock::get()?.unix_timestamp;
+
+    msg!("View key hash set for vault: {}", vault.key());
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Initialize the global protocol accounts
+//!
+//! One-time bring-up of every global PDA the shield/transfer/unshield flows depend on.
+//! Only the program's upgrade authority can run it, so the first caller after a
+//! deploy cannot claim the admin and guardian roles.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+use crate::state::{Denomination, ProtocolState, RateLimitState};
+
+/// Accounts for initializing the protocol
+#[derive(Accounts)]
+pub struct InitializeProtocol<'info> {
+    /// The protocol admin (payer for all global accounts); must be the upgrade authority
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// This program
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Ashborn>,
+
+    /// This program's data account, holding its upgrade authority
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ AshbornError::InvalidAdmin,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    /// Global protocol configuration
+    #[account(
+        init,
+        payer = admin,
+        space = ProtocolState::SIZE,
+        seeds = [b"protocol_state"],
+        bump
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Deposit rate limiter
+    #[account(
+        init,
+        payer = admin,
+        space = RateLimitState::SIZE,
+        seeds = [b"rate_limit"],
+        bump
+    )]
+    pub rate_limit: Box<Account<'info, RateLimitState>>,
+
+    /// System program for account creation
+    pub system_program: Program<'info, System>,
+}
+
+/// Initialize the protocol state and the deposit rate limiter
+///
+/// # Arguments
+/// * `ctx` - The instruction context
+/// * `fee_bps` - Unshield fee in basis points
+/// * `fee_recipient` - Account that receives protocol fees
+/// * `min_privacy_delay` - Minimum seconds before a note can be unshielded
+///
+/// # Effects
+/// - Creates ProtocolState with the upgrade authority as admin and guardian
+/// - Commitment trees are created per mint by `initialize_pool`
+/// - Creates RateLimitState with default limits
+pub fn handler(
+    ctx: Context<InitializeProtocol>,
+    fee_bps: u16,
+    fee_recipient: Pubkey,
+    min_privacy_delay: i64,
+) -> Result<()> {
+    require!(fee_bps <= ProtocolState::MAX_FEE_BPS, AshbornError::FeeTooHigh);
+    require!(fee_recipient != Pubkey::default(), AshbornError::InvalidFeeRecipient);
+    require!(min_privacy_delay >= 0, AshbornError::InvalidPrivacyDelay);
+
+    // 1. Rate limits are managed by the admin
+    ctx.accounts
+        .rate_limit
+        .initialize(ctx.accounts.admin.key(), ctx.bumps.rate_limit);
+
+    // 2. Protocol configuration
+    let protocol_state = &mut ctx.accounts.protocol_state;
+    protocol_state.version = ProtocolState::CURRENT_VERSION;
+    protocol_state.admin = ctx.accounts.admin.key();
+    protocol_state.total_shielded = 0;
+    protocol_state.tx_count = 0;
+    protocol_state.fee_bps = fee_bps;
+    protocol_state.fee_recipient = fee_recipient;
+    protocol_state.paused = false;
+    protocol_state.min_privacy_delay = min_privacy_delay;
+    protocol_state.bump = ctx.bumps.protocol_state;
+    protocol_state.pending_admin = Pubkey::default();
+    protocol_state.guardian = ctx.accounts.admin.key();
+    protocol_state.change_nonce = 0;
+    protocol_state.denomination_delays = [0; Denomination::COUNT];
+
+    msg!("Protocol initialized by admin: {}", protocol_state.admin);
+    msg!("Fee: {} bps, privacy delay: {}s", fee_bps, min_privacy_delay);
+
+    Ok(())
+}
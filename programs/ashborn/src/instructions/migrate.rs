@@ -0,0 +1,592 @@
+//! Account layout migrations
+//!
+//! Upgrades accounts created before layout versioning. Notes and protocol
+//! state are resized by realloc (payer tops up rent); vaults stamp their version
+//! into reserved space. The layout is read from the account's version byte
+//! (see `layout_version`). Handlers reject anything not on the current version.
+//! Legacy nullifier PDAs are imported into their nullifier set and closed,
+//! releasing their rent to the fee recipient.
+//!
+//! Pools and notes from before per-mint commitment trees need more than a
+//! relayout: a pool gets its first tree shard and its token accounts move to
+//! the mint's pool authority, and an unspent note's commitment is re-appended
+//! to its mint's tree. Nothing on-chain records a legacy note's mint, so the
+//! admin co-signs the note migration to attest it. Notes from before v3 also
+//! don't record the sequence number their PDA was derived from; the owner
+//! passes it in and the address check confirms it.
+//!
+//! Commitment tree shards from v1 carried an append changelog between the
+//! header and the root history. Migration drops it and moves the history
+//! down behind the smaller header, shrinking the account.
+
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+use anchor_spl::token::{self, spl_token::instruction::AuthorityType, Mint, SetAuthority, Token, TokenAccount};
+
+use crate::errors::AshbornError;
+use crate::events::NullifierSpent;
+use crate::state::{
+    load_tree_init, load_tree_mut, CommitmentTree, CommitmentTreeV1, Nullifier, NullifierSet, PoolConfig, PoolConfigV0,
+    ProtocolState, ProtocolStateV0, ProtocolStateV1, ShadowVault, ShieldedNote, ShieldedNoteV0, ShieldedNoteV1,
+    ShieldedNoteV2,
+};
+use super::rollover;
+
+/// Accounts for migrating a vault
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    /// The vault being migrated
+    #[account(
+        mut,
+        seeds = [b"shadow_vault", owner.key().as_ref()],
+        bump = vault.bump,
+        has_one = owner @ AshbornError::Unauthorized,
+        constraint = !vault.is_current() @ AshbornError::AccountAlreadyMigrated,
+    )]
+    pub vault: Box<Account<'info, ShadowVault>>,
+
+    /// Vault owner
+    pub owner: Signer<'info>,
+}
+
+/// Accounts for migrating a note
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateNote<'info> {
+    /// The vault the note belongs to
+    #[account(
+        seeds = [b"shadow_vault", owner.key().as_ref()],
+        bump = vault.bump,
+        has_one = owner @ AshbornError::Unauthorized,
+    )]
+    pub vault: Box<Account<'info, ShadowVault>>,
+
+    /// CHECK: Legacy layout, validated (discriminator, length, PDA) in handler
+    #[account(mut, owner = crate::ID)]
+    pub note: UncheckedAccount<'info>,
+
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Pool configuration of the mint the note was shielded into
+    #[account(
+        mut,
+        seeds = [b"pool_config", pool_config.mint.as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Active commitment tree shard of the mint (receives the note's commitment)
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", pool_config.mint.as_ref(), &commitment_tree.load()?.tree_id.to_le_bytes()],
+        bump = commitment_tree.load()?.bump,
+        constraint = commitment_tree.key() == pool_config.commitment_tree @ AshbornError::InactiveCommitmentTree,
+        constraint = commitment_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Next commitment tree shard, only created if the active one is full
+    /// CHECK: PDA verified by seeds, created and initialized on rollover
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", pool_config.mint.as_ref(), &(commitment_tree.load()?.tree_id + 1).to_le_bytes()],
+        bump,
+    )]
+    pub next_commitment_tree: UncheckedAccount<'info>,
+
+    /// Vault owner (pays for the extra rent)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Protocol admin (attests the note's mint)
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for migrating the protocol state
+#[derive(Accounts)]
+pub struct MigrateProtocolState<'info> {
+    /// CHECK: Legacy layout, validated (discriminator, length) in handler
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump,
+        owner = crate::ID,
+    )]
+    pub protocol_state: UncheckedAccount<'info>,
+
+    /// Protocol admin (pays for the extra rent)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for moving a pool onto its own commitment tree and pool authority
+#[derive(Accounts)]
+#[instruction(root_history_len: u32)]
+pub struct MigratePool<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The pool's token mint
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Legacy layout, validated (discriminator, length) in handler
+    #[account(
+        mut,
+        seeds = [b"pool_config", mint.key().as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub pool_config: UncheckedAccount<'info>,
+
+    /// First commitment tree shard for this mint
+    #[account(
+        init,
+        payer = admin,
+        space = CommitmentTree::space(root_history_len),
+        seeds = [b"commitment_tree".as_ref(), mint.key().as_ref(), &0u64.to_le_bytes()],
+        bump,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Shielded pool token account
+    #[account(
+        mut,
+        seeds = [b"shielded_pool", mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Fee vault token account
+    #[account(
+        mut,
+        seeds = [b"fee_vault", mint.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Shared pool authority PDA the token accounts are handed over from
+    /// CHECK: PDA authority, verified by seeds
+    #[account(
+        seeds = [b"pool_authority"],
+        bump,
+    )]
+    pub legacy_pool_authority: UncheckedAccount<'info>,
+
+    /// Pool authority PDA for this mint
+    /// CHECK: PDA authority, verified by seeds
+    #[account(
+        seeds = [b"pool_authority", mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_authority: UncheckedAccount<'info>,
+
+    /// Protocol admin (pays for the tree and the extra rent)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for dropping the changelog from a v1 commitment tree shard
+#[derive(Accounts)]
+pub struct MigrateCommitmentTree<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// CHECK: Legacy layout, validated (discriminator, length, PDA) in handler
+    #[account(mut, owner = crate::ID)]
+    pub commitment_tree: UncheckedAccount<'info>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for importing a legacy nullifier PDA
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct MigrateNullifier<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Legacy nullifier PDA, closed once imported
+    #[account(
+        mut,
+        seeds = [b"nullifier", nullifier_hash.as_ref()],
+        bump = legacy_nullifier.bump,
+        close = fee_recipient,
+    )]
+    pub legacy_nullifier: Box<Account<'info, Nullifier>>,
+
+    /// Nullifier set shard the nullifier moves into
+    #[account(
+        mut,
+        seeds = [b"nullifier_set".as_ref(), &nullifier_hash[31..]],
+        bump = nullifier_set.load()?.bump,
+        constraint = nullifier_set.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub nullifier_set: AccountLoader<'info, NullifierSet>,
+
+    /// Protocol fee recipient (receives the reclaimed rent)
+    /// CHECK: Only receives lamports, checked against the protocol state
+    #[account(mut, address = protocol_state.fee_recipient @ AshbornError::InvalidFeeRecipient)]
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Stamp the current version on a pre-versioning vault
+pub fn vault_handler(ctx: Context<MigrateVault>) -> Result<()> {
+    let vault = &mut ctx.accounts.vault;
+    let old_version = vault.version;
+    vault.version = ShadowVault::CURRENT_VERSION;
+
+    msg!("Vault migrated: v{} -> v{}", old_version, vault.version);
+
+    Ok(())
+}
+
+/// Upgrade an older note to the current layout
+///
+/// A pre-mint note is assigned to the attested mint and, if unspent, its
+/// commitment is re-appended to the mint's active tree shard; the shared
+/// pre-mint tree is no longer accepted for proofs. Every note gets its
+/// sequence number `seq` (the PDA seed) and its leaf index as separate fields.
+pub fn note_handler(ctx: Context<MigrateNote>, seq: u32) -> Result<()> {
+    let info = ctx.accounts.note.to_account_info();
+    let mint = ctx.accounts.pool_config.mint;
+    let (mut legacy, pre_mint): (ShieldedNoteV2, bool) =
+        match layout_version(&info, &ShieldedNote::DISCRIMINATOR, ShieldedNoteV0::SIZE)? {
+            0 => (read_legacy::<ShieldedNoteV0>(&info, ShieldedNoteV0::SIZE)?.into_v1().into_v2(mint), true),
+            1 => (read_legacy::<ShieldedNoteV1>(&info, ShieldedNoteV1::SIZE)?.into_v2(mint), true),
+            2 => (read_legacy(&info, ShieldedNoteV2::SIZE)?, false),
+            ShieldedNote::CURRENT_VERSION => return Err(AshbornError::AccountAlreadyMigrated.into()),
+            _ => return Err(AshbornError::InvalidAccountLayout.into()),
+        };
+
+    // The note must be this vault's note at its own PDA, in this pool
+    require_keys_eq!(legacy.vault, ctx.accounts.vault.key(), AshbornError::Unauthorized);
+    require_keys_eq!(legacy.mint, mint, AshbornError::InvalidMint);
+    verify_note_address(&info.key(), &ctx.accounts.vault.key(), seq, legacy.bump)?;
+
+    let reinserted = if pre_mint && !legacy.spent {
+        Some(rollover::append_commitment(
+            &mut ctx.accounts.pool_config,
+            &ctx.accounts.commitment_tree,
+            &ctx.accounts.next_commitment_tree,
+            ctx.bumps.next_commitment_tree,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+            legacy.commitment,
+        )?)
+    } else {
+        None
+    };
+
+    // A re-appended note now lives at its leaf in the mint's tree
+    if let Some(inserted) = &reinserted {
+        legacy.index = inserted.index;
+    }
+    let note = legacy.upgrade(seq);
+    write_upgraded(
+        &info,
+        &ctx.accounts.owner,
+        &ctx.accounts.system_program,
+        ShieldedNote::SIZE,
+        &note,
+    )?;
+
+    if let Some(inserted) = reinserted {
+        msg!("Commitment re-appended at leaf {}", inserted.index);
+        emit_cpi!(inserted);
+    }
+    msg!(
+        "Note #{} (leaf {}) migrated to v{} for mint {}",
+        seq,
+        note.leaf_index,
+        ShieldedNote::CURRENT_VERSION,
+        mint
+    );
+
+    Ok(())
+}
+
+/// Upgrade an older protocol state to the current layout
+pub fn protocol_state_handler(ctx: Context<MigrateProtocolState>) -> Result<()> {
+    let info = ctx.accounts.protocol_state.to_account_info();
+    let legacy: ProtocolStateV1 = match layout_version(&info, &ProtocolState::DISCRIMINATOR, ProtocolStateV0::SIZE)? {
+        0 => read_legacy::<ProtocolStateV0>(&info, ProtocolStateV0::SIZE)?.into_v1(),
+        1 => read_legacy(&info, ProtocolStateV1::SIZE)?,
+        ProtocolState::CURRENT_VERSION => return Err(AshbornError::AccountAlreadyMigrated.into()),
+        _ => return Err(AshbornError::InvalidAccountLayout.into()),
+    };
+
+    require_keys_eq!(legacy.admin, ctx.accounts.admin.key(), AshbornError::InvalidAdmin);
+
+    write_upgraded(
+        &info,
+        &ctx.accounts.admin,
+        &ctx.accounts.system_program,
+        ProtocolState::SIZE,
+        &legacy.upgrade(),
+    )?;
+
+    msg!("Protocol state migrated to v{}", ProtocolState::CURRENT_VERSION);
+
+    Ok(())
+}
+
+/// Give a pre-mint pool its first tree shard and its own pool authority (admin only)
+pub fn pool_handler(ctx: Context<MigratePool>, root_history_len: u32) -> Result<()> {
+    let info = ctx.accounts.pool_config.to_account_info();
+    let legacy: PoolConfigV0 = match layout_version(&info, &PoolConfig::DISCRIMINATOR, PoolConfigV0::SIZE)? {
+        0 => read_legacy(&info, PoolConfigV0::SIZE)?,
+        PoolConfig::CURRENT_VERSION => return Err(AshbornError::AccountAlreadyMigrated.into()),
+        _ => return Err(AshbornError::InvalidAccountLayout.into()),
+    };
+    let mint = ctx.accounts.mint.key();
+    require_keys_eq!(legacy.mint, mint, AshbornError::InvalidMint);
+
+    // 1. First tree shard, owned by the pool config like a freshly created pool's
+    {
+        let (mut tree, mut history) = load_tree_init(&ctx.accounts.commitment_tree)?;
+        tree.initialize(info.key(), mint, 0, ctx.bumps.commitment_tree, &mut history)?;
+    }
+
+    write_upgraded(
+        &info,
+        &ctx.accounts.admin,
+        &ctx.accounts.system_program,
+        PoolConfig::SIZE,
+        &legacy.upgrade(ctx.accounts.commitment_tree.key()),
+    )?;
+
+    // 2. Hand both token accounts to the mint's pool authority
+    let legacy_seeds = &[b"pool_authority".as_ref(), &[ctx.bumps.legacy_pool_authority]];
+    let signer_seeds = &[&legacy_seeds[..]];
+    for account in [
+        ctx.accounts.pool_token_account.to_account_info(),
+        ctx.accounts.fee_vault.to_account_info(),
+    ] {
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                SetAuthority {
+                    current_authority: ctx.accounts.legacy_pool_authority.to_account_info(),
+                    account_or_mint: account,
+                },
+                signer_seeds,
+            ),
+            AuthorityType::AccountOwner,
+            Some(ctx.accounts.pool_authority.key()),
+        )?;
+    }
+
+    msg!("Pool for {} migrated, commitment tree: {}", mint, ctx.accounts.commitment_tree.key());
+    msg!("Tree root history: {} roots", root_history_len);
+
+    Ok(())
+}
+
+/// Move a legacy nullifier PDA into its nullifier set and close it (admin only)
+///
+/// Legacy nullifiers don't record who paid for them, and linking a spend to
+/// its payer after the fact is exactly what the sets avoid, so the rent goes
+/// to the protocol's fee recipient rather than to the payer or the admin's
+/// own key.
+pub fn nullifier_handler(ctx: Context<MigrateNullifier>, nullifier_hash: [u8; 32]) -> Result<()> {
+    let reclaimed = ctx.accounts.legacy_nullifier.to_account_info().lamports();
+
+    {
+        let (mut nullifier_set, mut slots) = load_tree_mut(&ctx.accounts.nullifier_set)?;
+        nullifier_set.insert(nullifier_hash, &mut slots)?;
+    }
+
+    // Indexers replaying spends see it as spent when it originally was
+    emit_cpi!(NullifierSpent {
+        nullifier: nullifier_hash,
+        spent_at: ctx.accounts.legacy_nullifier.spent_at,
+    });
+
+    msg!("Nullifier {:?} migrated, {} lamports reclaimed", &nullifier_hash[..8], reclaimed);
+
+    Ok(())
+}
+
+/// Drop the changelog from a v1 commitment tree shard (admin only)
+///
+/// Roots, leaves and the root history carry over unchanged. The account
+/// shrinks; the rent it no longer needs stays in it.
+pub fn commitment_tree_handler(ctx: Context<MigrateCommitmentTree>) -> Result<()> {
+    let info = ctx.accounts.commitment_tree.to_account_info();
+    let legacy = read_tree_v1(&info.try_borrow_data()?)?;
+
+    let expected = Pubkey::create_program_address(
+        &[
+            b"commitment_tree",
+            legacy.mint.as_ref(),
+            &legacy.tree_id.to_le_bytes(),
+            &[legacy.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| error!(AshbornError::InvalidAccountLayout))?;
+    require_keys_eq!(expected, info.key(), AshbornError::InvalidAccountLayout);
+
+    let new_size = relayout_tree_v1(&mut info.try_borrow_mut_data()?, &legacy);
+    info.realloc(new_size, false)?;
+
+    msg!(
+        "Commitment tree {} shard {} migrated to v{}",
+        legacy.mint,
+        legacy.tree_id,
+        CommitmentTree::CURRENT_VERSION
+    );
+
+    Ok(())
+}
+
+/// Read the header of a v1 commitment tree account
+///
+/// The data must be exactly the v1 header followed by its root history.
+pub(crate) fn read_tree_v1(data: &[u8]) -> Result<CommitmentTreeV1> {
+    let header_end = 8 + size_of::<CommitmentTreeV1>();
+    require!(
+        data.len() > 8 && data[..8] == CommitmentTree::DISCRIMINATOR,
+        AshbornError::InvalidAccountLayout
+    );
+
+    // Current trees are a smaller header plus whole roots, which a v1 tree never is
+    let current_header_end = 8 + size_of::<CommitmentTree>();
+    if data.len() >= current_header_end && (data.len() - current_header_end).is_multiple_of(32) {
+        let header: CommitmentTree = bytemuck::pod_read_unaligned(&data[8..current_header_end]);
+        require!(header.is_current(), AshbornError::InvalidAccountLayout);
+        return Err(AshbornError::AccountAlreadyMigrated.into());
+    }
+
+    require!(data.len() >= header_end, AshbornError::InvalidAccountLayout);
+    let legacy: CommitmentTreeV1 = bytemuck::pod_read_unaligned(&data[8..header_end]);
+    require!(
+        legacy.version == 1 && data.len() == header_end + 32 * legacy.root_history_len as usize,
+        AshbornError::InvalidAccountLayout
+    );
+
+    Ok(legacy)
+}
+
+/// Rewrite v1 tree data in place as the current header plus the same root
+/// history, returning the account size the data now needs
+pub(crate) fn relayout_tree_v1(data: &mut [u8], legacy: &CommitmentTreeV1) -> usize {
+    let history = 8 + size_of::<CommitmentTreeV1>()..data.len();
+    let header_end = 8 + size_of::<CommitmentTree>();
+
+    data.copy_within(history, header_end);
+    data[8..header_end].copy_from_slice(bytemuck::bytes_of(&legacy.upgrade()));
+
+    CommitmentTree::space(legacy.root_history_len)
+}
+
+/// Check that `note` is the vault's note PDA for sequence number `seq`
+///
+/// Notes are created at `[b"shielded_note", vault, seq]` with the vault's u32
+/// note counter. Legacy layouts don't record it: their `index` is the
+/// commitment's leaf for deposits, so the caller supplies `seq`.
+pub(crate) fn verify_note_address(note: &Pubkey, vault: &Pubkey, seq: u32, bump: u8) -> Result<()> {
+    let expected = Pubkey::create_program_address(
+        &[b"shielded_note", vault.as_ref(), &seq.to_le_bytes(), &[bump]],
+        &crate::ID,
+    )
+    .map_err(|_| error!(AshbornError::InvalidAccountLayout))?;
+    require_keys_eq!(expected, *note, AshbornError::InvalidAccountLayout);
+
+    Ok(())
+}
+
+/// Layout version of an account, 0 for the original unversioned layout
+///
+/// Versioned layouts start with their version byte right after the
+/// discriminator. The unversioned layout has none and is the only one
+/// recognized by its length, which no versioned layout shares.
+pub(crate) fn layout_version(info: &AccountInfo, discriminator: &[u8; 8], unversioned_size: usize) -> Result<u8> {
+    let data = info.try_borrow_data()?;
+    require!(data.len() > 8, AshbornError::InvalidAccountLayout);
+    require!(&data[..8] == discriminator, AshbornError::InvalidAccountLayout);
+
+    Ok(if data.len() == unversioned_size { 0 } else { data[8] })
+}
+
+/// Deserialize a legacy layout whose version `layout_version` already checked
+pub(crate) fn read_legacy<T: AnchorDeserialize>(info: &AccountInfo, legacy_size: usize) -> Result<T> {
+    let data = info.try_borrow_data()?;
+    require!(data.len() == legacy_size, AshbornError::InvalidAccountLayout);
+
+    T::deserialize(&mut &data[8..]).map_err(|_| error!(AshbornError::InvalidAccountLayout))
+}
+
+/// Grow the account to `new_size`, top up rent and write the upgraded layout
+fn write_upgraded<'info, T: AccountSerialize>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_size: usize,
+    upgraded: &T,
+) -> Result<()> {
+    let rent_due = Rent::get()?
+        .minimum_balance(new_size)
+        .saturating_sub(info.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    info.realloc(new_size, false)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    upgraded.try_serialize(&mut writer)?;
+
+    Ok(())
+}
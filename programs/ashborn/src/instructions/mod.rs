@@ -1,13 +1,39 @@
 //! Instruction module exports
+//!
+//! Every module exposes a `handler`; the glob re-exports are only used for the
+//! `Accounts` structs, and `lib.rs` always calls handlers by module path.
 
+#![allow(ambiguous_glob_reexports)]
+
+pub mod admin;
 pub mod initialize;
+pub mod initialize_protocol;
 pub mod shield;
 pub mod transfer;
 pub mod reveal;
 pub mod unshield;
+pub mod timelock;
+pub mod circuit_breaker;
+pub mod pool;
+pub mod fees;
+pub mod migrate;
+pub mod nullifier;
+pub mod verifying_key;
+
+// Shared helpers, not instructions
+mod rollover;
 
+pub use admin::*;
 pub use initialize::*;
+pub use initialize_protocol::*;
 pub use shield::*;
 pub use transfer::*;
 pub use reveal::*;
 pub use unshield::*;
+pub use timelock::*;
+pub use circuit_breaker::*;
+pub use pool::*;
+pub use fees::*;
+pub use migrate::*;
+pub use nullifier::*;
+pub use verifying_key::*;
@@ -0,0 +1,103 @@
+//! Nullifier Storage Instructions
+//!
+//! Creates and grows the `NullifierSet` shards that transfer and unshield
+//! record spent nullifiers in.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use crate::state::{load_tree, load_tree_init, NullifierSet, ProtocolState, NULLIFIER_SET_GROWTH};
+use crate::errors::AshbornError;
+
+/// Accounts for creating a nullifier set shard
+#[derive(Accounts)]
+#[instruction(prefix: u8)]
+pub struct InitializeNullifierSet<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The new shard
+    #[account(
+        init,
+        payer = admin,
+        space = NullifierSet::space(NULLIFIER_SET_GROWTH),
+        seeds = [b"nullifier_set".as_ref(), &[prefix]],
+        bump,
+    )]
+    pub nullifier_set: AccountLoader<'info, NullifierSet>,
+
+    /// Protocol admin (payer)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for adding slots to a nullifier set shard
+#[derive(Accounts)]
+pub struct GrowNullifierSet<'info> {
+    /// The shard being grown
+    #[account(
+        mut,
+        seeds = [b"nullifier_set".as_ref(), &[nullifier_set.load()?.prefix]],
+        bump = nullifier_set.load()?.bump,
+        constraint = nullifier_set.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub nullifier_set: AccountLoader<'info, NullifierSet>,
+
+    /// Anyone can fund the extra rent once the shard is nearly full
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create an empty nullifier set shard (admin only)
+pub fn initialize_set_handler(ctx: Context<InitializeNullifierSet>, prefix: u8) -> Result<()> {
+    let (mut set, mut slots) = load_tree_init(&ctx.accounts.nullifier_set)?;
+    set.initialize(prefix, ctx.bumps.nullifier_set, &mut slots);
+
+    msg!("Nullifier set 0x{:02x} created with {} slots", prefix, slots.len());
+
+    Ok(())
+}
+
+/// Add `NULLIFIER_SET_GROWTH` empty slots to a nearly full shard (permissionless)
+///
+/// Permissionless so a full shard never blocks spends waiting on the admin;
+/// `NullifierSet::grown_len` bounds when and how far it can grow.
+pub fn grow_set_handler(ctx: Context<GrowNullifierSet>) -> Result<()> {
+    let info = ctx.accounts.nullifier_set.to_account_info();
+    let new_size = {
+        let (set, slots) = load_tree(&ctx.accounts.nullifier_set)?;
+        NullifierSet::space(set.grown_len(&slots)? as u32)
+    };
+
+    let rent_due = Rent::get()?
+        .minimum_balance(new_size)
+        .saturating_sub(info.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    info.realloc(new_size, false)?;
+
+    let (set, slots) = load_tree(&ctx.accounts.nullifier_set)?;
+    msg!("Nullifier set 0x{:02x} grown to {} slots", set.prefix, slots.len());
+
+    Ok(())
+}
@@ -0,0 +1,268 @@
+//! Initialize a shielded pool for a mint
+//!
+//! Creates the pool token account that holds user funds and the fee vault
+//! that holds protocol revenue, both controlled by the mint's pool authority
+//! PDA, plus the PoolConfig that caps TVL and tracks what the pool owes, the
+//! mint's outflow limit and its first commitment tree shard.
+//!
+//! A shard's root history can be grown later, past the length it can be
+//! created with, so proofs against older roots stay valid under heavy use.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::errors::AshbornError;
+use crate::events::{ConfigChange, ProtocolConfigChanged};
+use crate::state::{
+    load_tree_init, load_tree_mut, CommitmentTree, OutflowLimit, PoolConfig, ProtocolState,
+    MAX_ROOT_HISTORY, MAX_ROOT_HISTORY_GROWTH,
+};
+
+/// Accounts for initializing a pool
+#[derive(Accounts)]
+#[instruction(max_tvl: u64, root_history_len: u32)]
+pub struct InitializePool<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The token mint for this pool
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// Pool configuration (TVL cap, solvency accounting)
+    #[account(
+        init,
+        payer = admin,
+        space = PoolConfig::SIZE,
+        seeds = [b"pool_config", mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Outflow circuit breaker for this mint (unshield requires it)
+    #[account(
+        init,
+        payer = admin,
+        space = OutflowLimit::SIZE,
+        seeds = [b"outflow_limit", mint.key().as_ref()],
+        bump,
+    )]
+    pub outflow_limit: Box<Account<'info, OutflowLimit>>,
+
+    /// First commitment tree shard for this mint
+    #[account(
+        init,
+        payer = admin,
+        space = CommitmentTree::space(root_history_len),
+        seeds = [b"commitment_tree".as_ref(), mint.key().as_ref(), &0u64.to_le_bytes()],
+        bump,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Shielded pool token account (user funds)
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"shielded_pool", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool_authority,
+    )]
+    pub pool_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Fee vault token account (protocol revenue)
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"fee_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool_authority,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Pool authority PDA for this mint (controls pool and fee vault)
+    /// CHECK: PDA authority for pool, verified by seeds
+    #[account(
+        seeds = [b"pool_authority", mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// Protocol admin (payer)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for updating a pool's TVL cap
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPoolCap<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Pool configuration
+    #[account(
+        mut,
+        seeds = [b"pool_config", pool_config.mint.as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for growing a commitment tree shard's root history
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GrowRootHistory<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The shard being grown
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", commitment_tree.load()?.mint.as_ref(), &commitment_tree.load()?.tree_id.to_le_bytes()],
+        bump = commitment_tree.load()?.bump,
+        constraint = commitment_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Protocol admin (pays for the extra rent)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Create the pool, fee vault, pool config, outflow limit and first commitment tree for a mint
+pub fn handler(ctx: Context<InitializePool>, max_tvl: u64, root_history_len: u32) -> Result<()> {
+    require!(max_tvl > 0, AshbornError::ZeroAmount);
+
+    let mint = ctx.accounts.mint.key();
+    let pool_config = &mut ctx.accounts.pool_config;
+    pool_config.version = PoolConfig::CURRENT_VERSION;
+    pool_config.mint = mint;
+    pool_config.max_tvl = max_tvl;
+    pool_config.total_shielded = 0;
+    pool_config.bump = ctx.bumps.pool_config;
+    pool_config.commitment_tree = ctx.accounts.commitment_tree.key();
+
+    // Defaults until the admin tunes it with set_outflow_limit
+    ctx.accounts
+        .outflow_limit
+        .initialize(mint, max_tvl, ctx.bumps.outflow_limit);
+
+    // The mint's trees are owned by its pool config (history length follows the account size)
+    {
+        let (mut tree, mut history) = load_tree_init(&ctx.accounts.commitment_tree)?;
+        tree.initialize(pool_config.key(), mint, 0, ctx.bumps.commitment_tree, &mut history)?;
+    }
+
+    msg!("Shielded pool initialized for mint: {}", ctx.accounts.mint.key());
+    msg!("Pool: {}", ctx.accounts.pool_token_account.key());
+    msg!("Fee vault: {}", ctx.accounts.fee_vault.key());
+    msg!("TVL cap: {}", max_tvl);
+    msg!(
+        "Outflow limit: {} per {} slots",
+        ctx.accounts.outflow_limit.max_outflow_per_epoch,
+        OutflowLimit::DEFAULT_EPOCH_SLOTS
+    );
+    msg!("Commitment tree: {} ({} roots)", ctx.accounts.commitment_tree.key(), root_history_len);
+
+    Ok(())
+}
+
+/// Update the TVL cap (applies to new deposits only)
+pub fn set_cap_handler(ctx: Context<SetPoolCap>, max_tvl: u64) -> Result<()> {
+    require!(max_tvl > 0, AshbornError::ZeroAmount);
+
+    let pool_config = &mut ctx.accounts.pool_config;
+    let old_cap = pool_config.max_tvl;
+    pool_config.max_tvl = max_tvl;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::PoolCap {
+            mint: pool_config.mint,
+            max_tvl,
+        },
+    });
+
+    msg!("TVL cap for {}: {} -> {}", pool_config.mint, old_cap, max_tvl);
+
+    Ok(())
+}
+
+/// Add `additional` root history slots to a commitment tree shard (admin only)
+///
+/// Up to `MAX_ROOT_HISTORY_GROWTH` slots per call, `MAX_ROOT_HISTORY` in total.
+pub fn grow_root_history_handler(ctx: Context<GrowRootHistory>, additional: u32) -> Result<()> {
+    require!(
+        (1..=MAX_ROOT_HISTORY_GROWTH).contains(&additional),
+        AshbornError::InvalidRootHistoryLength
+    );
+    let new_len = ctx.accounts.commitment_tree.load()?.root_history_len + additional;
+    require!(new_len <= MAX_ROOT_HISTORY, AshbornError::InvalidRootHistoryLength);
+
+    let info = ctx.accounts.commitment_tree.to_account_info();
+    let new_size = CommitmentTree::space(new_len);
+    let rent_due = Rent::get()?
+        .minimum_balance(new_size)
+        .saturating_sub(info.lamports());
+    if rent_due > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.admin.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    info.realloc(new_size, false)?;
+
+    {
+        let (mut tree, mut history) = load_tree_mut(&ctx.accounts.commitment_tree)?;
+        tree.extend_root_history(&mut history)?;
+    }
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::RootHistoryGrown {
+            tree: info.key(),
+            root_history_len: new_len,
+        },
+    });
+
+    msg!("Commitment tree {} root history grown to {} roots", info.key(), new_len);
+
+    Ok(())
+}
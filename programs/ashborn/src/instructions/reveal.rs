@@ -1,14 +1,16 @@
 //! Updated Reveal Instruction with Real Range Proofs
 //!
-//! range_org-approved: Real Bulletproof verification
+//! range_org-approved: Groth16 range proofs (`range.circom`)
 
 use anchor_lang::prelude::*;
-use crate::state::{ShadowVault, ComplianceProof};
+use crate::state::{ShadowVault, ComplianceProof, ProtocolState, VerifyingKey};
 use crate::errors::AshbornError;
-use crate::zk::verify_range_proof;
+use crate::events::ComplianceProofStored;
+use crate::zk::{verify_ownership_proof, verify_range_proof};
 use crate::ProofType;
 
 /// Accounts for selective reveal
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(proof_type: ProofType, timestamp: u64)]
 pub struct SelectiveReveal<'info> {
@@ -17,6 +19,7 @@ pub struct SelectiveReveal<'info> {
         seeds = [b"shadow_vault", owner.key().as_ref()],
         bump = vault.bump,
         has_one = owner @ AshbornError::Unauthorized,
+        constraint = vault.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub vault: Account<'info, ShadowVault>,
 
@@ -30,6 +33,23 @@ pub struct SelectiveReveal<'info> {
     )]
     pub proof_record: Account<'info, ComplianceProof>,
 
+    /// Protocol state (pause switch)
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.paused @ AshbornError::ProtocolPaused,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Active verifying key for the proof type's circuit
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[verifying_key.circuit as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
     /// Owner
     #[account(mut)]
     pub owner: Signer<'info>,
@@ -41,13 +61,14 @@ pub struct SelectiveReveal<'info> {
 pub fn handler(
     ctx: Context<SelectiveReveal>,
     proof_type: ProofType,
-    timestamp: u64,
+    _timestamp: u64,
     range_min: u64,
     range_max: u64,
     proof_data: Vec<u8>,
     commitment: [u8; 32],
 ) -> Result<()> {
     let vault = &ctx.accounts.vault;
+    let verifying_key = &ctx.accounts.verifying_key;
     let proof_record = &mut ctx.accounts.proof_record;
     let clock = Clock::get()?;
 
@@ -57,24 +78,32 @@ pub fn handler(
     // 2. Verify proof based on type (range_org-approved)
     let is_valid = match proof_type {
         ProofType::RangeProof => {
-            // Verify Bulletproof range proof
-            verify_range_proof(&proof_data, &commitment, range_min, range_max)?
+            // Groth16 proof that the committed value is in [range_min, range_max]
+            verify_range_proof(verifying_key, &proof_data, &commitment, range_min, range_max)?
         }
         ProofType::OwnershipProof => {
-            // Verify ownership without range
-            verify_ownership_proof(&proof_data, vault.owner)?
+            // Groth16 proof of the secrets behind the vault's view key hash
+            require!(vault.view_key_hash != [0u8; 32], AshbornError::ViewKeyNotSet);
+            verify_ownership_proof(verifying_key, &proof_data, &vault.view_key_hash, &vault.key())?
         }
         ProofType::ComplianceProof => {
             // Verify combined compliance proof
-            verify_range_proof(&proof_data, &commitment, range_min, range_max)?
+            verify_range_proof(verifying_key, &proof_data, &commitment, range_min, range_max)?
         }
+        #[cfg(feature = "demo")]
         ProofType::Custom => {
             // Custom verification logic
             verify_custom_proof(&proof_data)?
         }
     };
 
-    require!(is_valid, AshbornError::InvalidRangeProof);
+    let proof_error = match proof_type {
+        ProofType::OwnershipProof => AshbornError::InvalidOwnershipProof,
+        _ => AshbornError::InvalidRangeProof,
+    };
+    if !is_valid {
+        return Err(proof_error.into());
+    }
 
     // 3. Store proof record
     proof_record.vault = vault.key();
@@ -86,6 +115,15 @@ pub fn handler(
     proof_record.expires_at = clock.unix_timestamp + 30 * 24 * 60 * 60; // 30 days
     proof_record.bump = ctx.bumps.proof_record;
 
+    emit_cpi!(ComplianceProofStored {
+        proof_record: proof_record.key(),
+        vault: vault.key(),
+        proof_type,
+        range_min,
+        range_max,
+        expires_at: proof_record.expires_at,
+    });
+
     msg!("Compliance proof stored successfully");
     msg!("Type: {:?}, Range: [{}, {}]", proof_type, range_min, range_max);
     msg!("Expires: {}", proof_record.expires_at);
@@ -93,16 +131,8 @@ pub fn handler(
     Ok(())
 }
 
-/// Verify ownership proof
-fn verify_ownership_proof(proof: &[u8], owner: Pubkey) -> Result<bool> {
-    // Verify the proof contains valid ownership claim
-    require!(proof.len() >= 64, AshbornError::InvalidOwnershipProof);
-    
-    // In production: verify signature or ZK proof of ownership
-    Ok(true)
-}
-
-/// Verify custom proof
+/// Verify custom proof (demo builds only: checks nothing but the length)
+#[cfg(feature = "demo")]
 fn verify_custom_proof(proof: &[u8]) -> Result<bool> {
     // Custom verification logic
     require!(proof.len() >= 32, AshbornError::CustomProofFailed);
@@ -0,0 +1,178 @@
+//! Commitment tree shard rollover
+//!
+//! Shield and transfer append to the mint's active shard. When it is full,
+//! the next shard (`commitment_tree` + mint + `tree_id + 1`) is created on the
+//! spot, made active in the mint's PoolConfig, and the commitment goes there
+//! instead. A batch that doesn't fit is split across the two shards.
+//! The new shard keeps the active one's root history length, up to what a
+//! tree can be created with; a longer history has to be grown again.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+
+use crate::errors::AshbornError;
+use crate::events::CommitmentInserted;
+use crate::state::{create_tree, load_tree_mut, CommitmentTree, PoolConfig, MAX_INITIAL_ROOT_HISTORY};
+
+/// Append a commitment to the active shard, rolling over if it is full
+///
+/// Returns the insertion event, which carries the commitment's global index.
+pub(crate) fn append_commitment<'info>(
+    pool_config: &mut Account<'info, PoolConfig>,
+    active_tree: &AccountLoader<'info, CommitmentTree>,
+    next_tree: &UncheckedAccount<'info>,
+    next_tree_bump: u8,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    commitment: [u8; 32],
+) -> Result<CommitmentInserted> {
+    let mut inserted = append_commitments(
+        pool_config,
+        active_tree,
+        next_tree,
+        next_tree_bump,
+        payer,
+        system_program,
+        &[commitment],
+    )?;
+    Ok(inserted.remove(0))
+}
+
+/// Append a batch of commitments, spilling into the next shard if the
+/// active one fills up part-way
+///
+/// Returns one insertion event per commitment, in order.
+pub(crate) fn append_commitments<'info>(
+    pool_config: &mut Account<'info, PoolConfig>,
+    active_tree: &AccountLoader<'info, CommitmentTree>,
+    next_tree: &UncheckedAccount<'info>,
+    next_tree_bump: u8,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    commitments: &[[u8; 32]],
+) -> Result<Vec<CommitmentInserted>> {
+    let mut inserted = Vec::with_capacity(commitments.len());
+    let spill = {
+        let (mut tree, mut history) = load_tree_mut(active_tree)?;
+        let fits = commitments.len().min(tree.remaining_capacity() as usize);
+        let (here, spill) = commitments.split_at(fits);
+        if !here.is_empty() {
+            let first = tree.append_commitments(here, &mut history)?;
+            inserted.extend(insertion_events(active_tree.key(), &tree, first, here));
+        }
+        if spill.is_empty() {
+            return Ok(inserted);
+        }
+        spill
+    };
+
+    let (tree_id, root_history_len) = {
+        let tree = active_tree.load()?;
+        (tree.tree_id + 1, tree.root_history_len.min(MAX_INITIAL_ROOT_HISTORY))
+    };
+
+    let id_bytes = tree_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[
+        b"commitment_tree",
+        pool_config.mint.as_ref(),
+        &id_bytes,
+        &[next_tree_bump],
+    ];
+    allocate_shard(
+        next_tree,
+        payer,
+        system_program,
+        CommitmentTree::space(root_history_len),
+        seeds,
+    )?;
+
+    let info = next_tree.to_account_info();
+    let (mut tree, mut history) = create_tree::<CommitmentTree>(&info)?;
+    tree.initialize(pool_config.key(), pool_config.mint, tree_id, next_tree_bump, &mut history)?;
+    let first = tree.append_commitments(spill, &mut history)?;
+    inserted.extend(insertion_events(next_tree.key(), &tree, first, spill));
+
+    pool_config.commitment_tree = next_tree.key();
+    msg!("Commitment tree for {} rolled over to shard {}", pool_config.mint, tree_id);
+
+    Ok(inserted)
+}
+
+/// Events for `leaves` appended to `tree` starting at leaf `first`
+fn insertion_events<'a>(
+    tree_key: Pubkey,
+    tree: &'a CommitmentTree,
+    first: u64,
+    leaves: &'a [[u8; 32]],
+) -> impl Iterator<Item = CommitmentInserted> + 'a {
+    (first..).zip(leaves).map(move |(leaf_index, leaf)| CommitmentInserted {
+        tree: tree_key,
+        index: tree.global_index(leaf_index),
+        leaf: *leaf,
+        new_root: tree.root,
+    })
+}
+
+/// Create the next shard PDA, tolerating lamports already sent to its address
+fn allocate_shard<'info>(
+    shard: &UncheckedAccount<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let signer_seeds = &[seeds];
+    let rent = Rent::get()?.minimum_balance(space);
+    let program = system_program.to_account_info();
+
+    if shard.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                program,
+                CreateAccount {
+                    from: payer.to_account_info(),
+                    to: shard.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    require!(shard.data_is_empty(), AshbornError::InactiveCommitmentTree);
+    let top_up = rent.saturating_sub(shard.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                program.clone(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: shard.to_account_info(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            program.clone(),
+            Allocate {
+                account_to_allocate: shard.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            program,
+            Assign {
+                account_to_assign: shard.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
+}
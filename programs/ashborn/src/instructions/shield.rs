@@ -4,12 +4,16 @@
 //! Privacy Cash integration point
 
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, CreateAccount};
 use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
-use crate::state::{ShadowVault, ShieldedNote, CommitmentTree, Denomination};
+use crate::state::{ShadowVault, ShieldedNote, CommitmentTree, Denomination, ProtocolState, RateLimitState, PoolConfig, CircuitId, VerifyingKey};
 use crate::errors::AshbornError;
-use crate::zk::{verify_shield_proof, create_commitment};
+use crate::events::NoteCreated;
+use crate::zk::verify_shield_proof;
+use super::rollover;
 
 /// Accounts for shield deposit
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ShieldDeposit<'info> {
     /// The user's vault
@@ -18,6 +22,7 @@ pub struct ShieldDeposit<'info> {
         seeds = [b"shadow_vault", owner.key().as_ref()],
         bump = vault.bump,
         has_one = owner @ AshbornError::Unauthorized,
+        constraint = vault.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub vault: Box<Account<'info, ShadowVault>>,
 
@@ -41,21 +46,68 @@ pub struct ShieldDeposit<'info> {
     )]
     pub pool_token_account: Box<Account<'info, TokenAccount>>,
 
+    /// Pool configuration (TVL cap, solvency accounting)
+    #[account(
+        mut,
+        seeds = [b"pool_config", mint.key().as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
     /// Pool authority PDA (controls pool withdrawals)
     /// CHECK: PDA authority for pool, verified by seeds
     #[account(
-        seeds = [b"pool_authority"],
+        seeds = [b"pool_authority", mint.key().as_ref()],
         bump,
     )]
     pub pool_authority: AccountInfo<'info>,
 
-    /// Global commitment tree
+    /// Protocol state (pause switch, TVL accounting)
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.paused @ AshbornError::ProtocolPaused,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Active verifying key for the shield circuit
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[CircuitId::Shield as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Active commitment tree shard for this mint
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", mint.key().as_ref(), &commitment_tree.load()?.tree_id.to_le_bytes()],
+        bump = commitment_tree.load()?.bump,
+        constraint = commitment_tree.key() == pool_config.commitment_tree @ AshbornError::InactiveCommitmentTree,
+        constraint = commitment_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Next commitment tree shard, only created if the active one is full
+    /// CHECK: PDA verified by seeds, created and initialized on rollover
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", mint.key().as_ref(), &(commitment_tree.load()?.tree_id + 1).to_le_bytes()],
+        bump,
+    )]
+    pub next_commitment_tree: UncheckedAccount<'info>,
+
+    /// Deposit rate limiter (protects the tree from spam)
     #[account(
         mut,
-        seeds = [b"commitment_tree"],
-        bump = commitment_tree.bump,
+        seeds = [b"rate_limit"],
+        bump = rate_limit.bump,
+        constraint = rate_limit.is_current() @ AshbornError::AccountNotMigrated,
     )]
-    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    pub rate_limit: Box<Account<'info, RateLimitState>>,
 
     /// The new shielded note
     #[account(
@@ -81,11 +133,10 @@ pub fn handler(
     amount: u64,
     commitment: [u8; 32],
     proof: Vec<u8>,
-    merkle_siblings: [[u8; 32]; 20],
 ) -> Result<()> {
     let vault = &mut ctx.accounts.vault;
     let note = &mut ctx.accounts.note;
-    let commitment_tree = &mut ctx.accounts.commitment_tree;
+    let protocol_state = &mut ctx.accounts.protocol_state;
     let clock = Clock::get()?;
 
     // 1. Validate denomination (privacy-preserving: fixed amounts only)
@@ -94,11 +145,15 @@ pub fn handler(
 
     msg!("Shield amount validated: {} lamports (tier {})", amount, denomination as u8);
 
-    // 2. Verify shield proof (proves commitment is well-formed)
-    let proof_valid = verify_shield_proof(&proof, amount, &commitment)?;
+    // 2. Rate limit and TVL cap checks before any work is done
+    ctx.accounts.rate_limit.record_deposit(clock.slot)?;
+    ctx.accounts.pool_config.record_deposit(amount)?;
+
+    // 3. Verify shield proof (proves commitment is well-formed)
+    let proof_valid = verify_shield_proof(&ctx.accounts.verifying_key, &proof, amount, &commitment)?;
     require!(proof_valid, AshbornError::InvalidCommitment);
 
-    // 3. Transfer tokens to shielded pool
+    // 4. Transfer tokens to shielded pool
     let transfer_ctx = CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
         Transfer {
@@ -109,98 +164,315 @@ pub fn handler(
     );
     token::transfer(transfer_ctx, amount)?;
 
-    // 4. Insert commitment into Merkle tree
-    let note_index = commitment_tree.insert_commitment(commitment, &merkle_siblings)?;
-
-    // 5. Create shielded note
+    // 5. Insert commitment into the active Merkle tree shard
+    let inserted = rollover::append_commitment(
+        &mut ctx.accounts.pool_config,
+        &ctx.accounts.commitment_tree,
+        &ctx.accounts.next_commitment_tree,
+        ctx.bumps.next_commitment_tree,
+        &ctx.accounts.owner,
+        &ctx.accounts.system_program,
+        commitment,
+    )?;
+    let leaf_index = inserted.index;
+
+    // 6. Create shielded note
+    note.version = ShieldedNote::CURRENT_VERSION;
     note.vault = vault.key();
+    note.mint = ctx.accounts.mint.key();
     note.commitment = commitment;
-    note.index = note_index;
+    note.index = vault.note_count + 1;
+    note.leaf_index = leaf_index;
     note.denomination_tier = denomination as u8;
     note.spent = false;
     note.created_at = clock.unix_timestamp;
-    note.unshield_after = clock.unix_timestamp + 24 * 60 * 60; // 24h privacy delay
+    note.unshield_after = protocol_state.unshield_after(clock.unix_timestamp, Some(denomination))?;
     note.bump = ctx.bumps.note;
 
-    // 6. Update vault state (no balance stored - privacy!)
+    // 7. Update vault state (no balance stored - privacy!)
     vault.note_count += 1;
     vault.last_activity = clock.unix_timestamp;
 
+    // 8. Protocol accounting
+    protocol_state.record_shield(amount)?;
+
+    emit_cpi!(inserted);
+    emit_cpi!(NoteCreated {
+        note: note.key(),
+        vault: note.vault,
+        commitment,
+        leaf_index,
+        denomination_tier: note.denomination_tier,
+        unshield_after: note.unshield_after,
+    });
+
     msg!("Assets shielded successfully");
-    msg!("Note #{} created with commitment: {:?}", note_index, &commitment[..8]);
+    msg!("Note #{} created at leaf {} with commitment: {:?}", note.index, leaf_index, &commitment[..8]);
 
     Ok(())
 }
 
 // ============================================================================
-// SIMPLIFIED SHIELD (SDK-Compatible) - For Demo Purposes
-// Matches SDK signature: shield(amount, commitment)
+// BATCH SHIELD - Many notes of one denomination in a single transaction
 // ============================================================================
 
-/// Simplified accounts for SDK-compatible shield
+/// Most commitments a batch shield accepts
+///
+/// Each one carries a 256-byte proof and a note account, so the transaction
+/// size limit is usually hit first.
+pub const MAX_SHIELD_BATCH: usize = 4;
+
+/// Accounts for batch shield
+///
+/// The new note PDAs are passed as remaining accounts, one per commitment,
+/// in order of their note sequence number.
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Shield<'info> {
+pub struct ShieldBatch<'info> {
     /// The user's vault
     #[account(
         mut,
         seeds = [b"shadow_vault", owner.key().as_ref()],
         bump = vault.bump,
         has_one = owner @ AshbornError::Unauthorized,
+        constraint = vault.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub vault: Box<Account<'info, ShadowVault>>,
 
-    /// The new shielded note
+    /// User's token account to deposit from
     #[account(
-        init,
-        payer = owner,
-        space = ShieldedNote::SIZE,
-        seeds = [b"shielded_note", vault.key().as_ref(), &(vault.note_count + 1).to_le_bytes()],
+        mut,
+        constraint = user_token_account.mint == mint.key() @ AshbornError::InvalidMint,
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// The token mint being shielded
+    pub mint: Account<'info, Mint>,
+
+    /// Shielded pool token account (PDA validated!)
+    #[account(
+        mut,
+        seeds = [b"shielded_pool", mint.key().as_ref()],
         bump,
+        token::mint = mint,
+        token::authority = pool_authority,
     )]
-    pub note: Box<Account<'info, ShieldedNote>>,
+    pub pool_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Pool configuration (TVL cap, solvency accounting)
+    #[account(
+        mut,
+        seeds = [b"pool_config", mint.key().as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Pool authority PDA (controls pool withdrawals)
+    /// CHECK: PDA authority for pool, verified by seeds
+    #[account(
+        seeds = [b"pool_authority", mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// Protocol state (pause switch, TVL accounting)
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.paused @ AshbornError::ProtocolPaused,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Active verifying key for the shield circuit
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[CircuitId::Shield as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Active commitment tree shard for this mint
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", mint.key().as_ref(), &commitment_tree.load()?.tree_id.to_le_bytes()],
+        bump = commitment_tree.load()?.bump,
+        constraint = commitment_tree.key() == pool_config.commitment_tree @ AshbornError::InactiveCommitmentTree,
+        constraint = commitment_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Next commitment tree shard, only created if the active one fills up
+    /// CHECK: PDA verified by seeds, created and initialized on rollover
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", mint.key().as_ref(), &(commitment_tree.load()?.tree_id + 1).to_le_bytes()],
+        bump,
+    )]
+    pub next_commitment_tree: UncheckedAccount<'info>,
+
+    /// Deposit rate limiter (each commitment counts as a deposit)
+    #[account(
+        mut,
+        seeds = [b"rate_limit"],
+        bump = rate_limit.bump,
+        constraint = rate_limit.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub rate_limit: Box<Account<'info, RateLimitState>>,
 
     /// Owner (payer)
     #[account(mut)]
     pub owner: Signer<'info>,
 
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
-/// Simplified shield handler matching SDK signature
-/// Creates a commitment record without full Merkle tree / ZK proof validation
-/// Suitable for demo/devnet usage
-pub fn shield_simple_handler(
-    ctx: Context<Shield>,
+/// Shield several notes of the same denomination with one transfer and one
+/// commitment tree root update
+pub fn batch_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, ShieldBatch<'info>>,
     amount: u64,
-    commitment: [u8; 32],
+    commitments: Vec<[u8; 32]>,
+    proofs: Vec<Vec<u8>>,
 ) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
-    let note = &mut ctx.accounts.note;
+    let count = commitments.len();
+    require!(
+        (1..=MAX_SHIELD_BATCH).contains(&count)
+            && proofs.len() == count
+            && ctx.remaining_accounts.len() == count,
+        AshbornError::InvalidBatchSize
+    );
     let clock = Clock::get()?;
 
     // 1. Validate denomination (privacy-preserving: fixed amounts only)
     let denomination = Denomination::from_amount(amount)
         .ok_or(AshbornError::InvalidDenomination)?;
+    let total = amount
+        .checked_mul(count as u64)
+        .ok_or(AshbornError::Overflow)?;
 
-    msg!("[Shield] Amount validated: {} lamports (tier {})", amount, denomination as u8);
+    msg!("Batch shield validated: {} x {} lamports (tier {})", count, amount, denomination as u8);
 
-    // 2. Create shielded note (simplified - no Merkle tree insertion)
-    note.vault = vault.key();
-    note.commitment = commitment;
-    note.index = (vault.note_count + 1) as u64;
-    note.denomination_tier = denomination as u8;
-    note.spent = false;
-    note.created_at = clock.unix_timestamp;
-    note.unshield_after = clock.unix_timestamp + 24 * 60 * 60; // 24h privacy delay
-    note.bump = ctx.bumps.note;
+    // 2. Rate limit and TVL cap checks before any work is done
+    for _ in 0..count {
+        ctx.accounts.rate_limit.record_deposit(clock.slot)?;
+    }
+    ctx.accounts.pool_config.record_deposit(total)?;
 
-    // 3. Update vault state
-    vault.note_count += 1;
+    // 3. Verify every shield proof
+    for (proof, commitment) in proofs.iter().zip(&commitments) {
+        let proof_valid = verify_shield_proof(&ctx.accounts.verifying_key, proof, amount, commitment)?;
+        require!(proof_valid, AshbornError::InvalidCommitment);
+    }
+
+    // 4. One transfer for the whole batch
+    let transfer_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.pool_token_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        },
+    );
+    token::transfer(transfer_ctx, total)?;
+
+    // 5. Append all commitments with a single root update
+    let inserted = rollover::append_commitments(
+        &mut ctx.accounts.pool_config,
+        &ctx.accounts.commitment_tree,
+        &ctx.accounts.next_commitment_tree,
+        ctx.bumps.next_commitment_tree,
+        &ctx.accounts.owner,
+        &ctx.accounts.system_program,
+        &commitments,
+    )?;
+
+    // 6. Create the shielded notes
+    let protocol_state = &mut ctx.accounts.protocol_state;
+    let vault = &mut ctx.accounts.vault;
+    let unshield_after = protocol_state.unshield_after(clock.unix_timestamp, Some(denomination))?;
+    for (i, (note_info, event)) in ctx.remaining_accounts.iter().zip(inserted).enumerate() {
+        let seq = vault.note_count + 1 + i as u32;
+        let note = ShieldedNote {
+            version: ShieldedNote::CURRENT_VERSION,
+            vault: vault.key(),
+            mint: ctx.accounts.mint.key(),
+            commitment: event.leaf,
+            encrypted_amount: [0u8; 48],
+            index: seq,
+            leaf_index: event.index,
+            denomination_tier: denomination as u8,
+            spent: false,
+            created_at: clock.unix_timestamp,
+            unshield_after,
+            bump: 0,
+        };
+        let note_key = create_note(
+            note_info,
+            note,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit_cpi!(NoteCreated {
+            note: note_key,
+            vault: vault.key(),
+            commitment: event.leaf,
+            leaf_index: event.index,
+            denomination_tier: denomination as u8,
+            unshield_after,
+        });
+        emit_cpi!(event);
+    }
+
+    // 7. Update vault state (no balance stored - privacy!)
+    vault.note_count += count as u32;
     vault.last_activity = clock.unix_timestamp;
 
-    msg!("[Shield] Note #{} created with commitment: {:?}", note.index, &commitment[..8]);
-    msg!("[Shield] The shadows have accepted your offering.");
+    // 8. Protocol accounting
+    protocol_state.record_shield(total)?;
+
+    msg!("Batch shielded: {} notes", count);
 
     Ok(())
 }
 
+/// Create the note PDA at the note's sequence number (`index`) in its vault,
+/// write `note` to it and return its address
+fn create_note<'info>(
+    note_info: &AccountInfo<'info>,
+    mut note: ShieldedNote,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<Pubkey> {
+    let seq_bytes = note.index.to_le_bytes();
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"shielded_note", note.vault.as_ref(), &seq_bytes],
+        &crate::ID,
+    );
+    require_keys_eq!(expected, note_info.key(), AshbornError::InvalidAccountLayout);
+
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            CreateAccount {
+                from: payer.to_account_info(),
+                to: note_info.clone(),
+            },
+            &[&[b"shielded_note", note.vault.as_ref(), &seq_bytes, &[bump]]],
+        ),
+        Rent::get()?.minimum_balance(ShieldedNote::SIZE),
+        ShieldedNote::SIZE as u64,
+        &crate::ID,
+    )?;
+
+    note.bump = bump;
+    let mut data = note_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    note.try_serialize(&mut writer)?;
+
+    Ok(expected)
+}
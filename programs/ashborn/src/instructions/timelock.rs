@@ -0,0 +1,274 @@
+//! Timelocked configuration changes
+//!
+//! queue (admin) -> wait `PendingChange::MIN_DELAY` -> execute (anyone),
+//! or cancel (admin) at any point before execution.
+//!
+//! Verifying key activations pass the key account at queue time, which must
+//! already be complete (and so immutable), and the registry plus the
+//! circuit's current key at execution.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+use crate::events::{ChangeCancelled, ChangeExecuted, ChangeQueued};
+use crate::state::{
+    ParameterChange, PendingChange, ProtocolState, RateLimitState, VerifyingKey, VerifyingKeyRegistry,
+};
+
+/// Accounts for queueing a change
+#[event_cpi]
+#[derive(Accounts)]
+pub struct QueueChange<'info> {
+    /// Global protocol configuration
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The queued change
+    #[account(
+        init,
+        payer = admin,
+        space = PendingChange::SIZE,
+        seeds = [b"pending_change".as_ref(), &protocol_state.change_nonce.to_le_bytes()],
+        bump,
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    /// Key to activate (required for `ActivateVerifyingKey`)
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[verifying_key.circuit as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Option<Box<Account<'info, VerifyingKey>>>,
+
+    /// Protocol admin (payer)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for executing a change (permissionless)
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    /// Global protocol configuration
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Deposit rate limiter
+    #[account(
+        mut,
+        seeds = [b"rate_limit"],
+        bump = rate_limit.bump,
+        constraint = rate_limit.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub rate_limit: Box<Account<'info, RateLimitState>>,
+
+    /// Active version per circuit (required for `ActivateVerifyingKey`)
+    #[account(
+        mut,
+        seeds = [b"vk_registry"],
+        bump = registry.bump,
+        constraint = registry.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub registry: Option<Box<Account<'info, VerifyingKeyRegistry>>>,
+
+    /// Key being activated (required for `ActivateVerifyingKey`)
+    #[account(
+        mut,
+        seeds = [b"verifying_key".as_ref(), &[verifying_key.circuit as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Option<Box<Account<'info, VerifyingKey>>>,
+
+    /// The circuit's currently active key, retired by the activation
+    /// (required if the circuit has one)
+    #[account(
+        mut,
+        seeds = [b"verifying_key".as_ref(), &[previous_key.circuit as u8], &previous_key.version.to_le_bytes()],
+        bump = previous_key.bump,
+        constraint = previous_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub previous_key: Option<Box<Account<'info, VerifyingKey>>>,
+
+    /// The change being executed (closed, rent back to proposer)
+    #[account(
+        mut,
+        seeds = [b"pending_change".as_ref(), &pending_change.id.to_le_bytes()],
+        bump = pending_change.bump,
+        has_one = proposer,
+        close = proposer,
+        constraint = pending_change.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    /// CHECK: Rent receiver, verified by `has_one` on pending_change
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+/// Accounts for cancelling a change
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelChange<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The change being cancelled (closed, rent back to admin)
+    #[account(
+        mut,
+        seeds = [b"pending_change".as_ref(), &pending_change.id.to_le_bytes()],
+        bump = pending_change.bump,
+        close = admin,
+        constraint = pending_change.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pending_change: Box<Account<'info, PendingChange>>,
+
+    /// Protocol admin
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+/// Queue a change that can be executed at `eta` or later
+pub fn queue_handler(ctx: Context<QueueChange>, change: ParameterChange, eta: i64) -> Result<()> {
+    let clock = Clock::get()?;
+
+    change.validate()?;
+    require!(
+        eta >= clock.unix_timestamp.saturating_add(PendingChange::MIN_DELAY),
+        AshbornError::TimelockTooShort
+    );
+
+    // The key users review during the timelock is the one that goes live
+    if let ParameterChange::ActivateVerifyingKey { circuit, version } = change {
+        let key = ctx
+            .accounts
+            .verifying_key
+            .as_ref()
+            .ok_or(AshbornError::InvalidVerifyingKey)?;
+        require!(
+            key.circuit == circuit && key.version == version,
+            AshbornError::InvalidVerifyingKey
+        );
+        key.check_activatable()?;
+    }
+
+    let protocol_state = &mut ctx.accounts.protocol_state;
+    let pending_change = &mut ctx.accounts.pending_change;
+
+    pending_change.version = PendingChange::CURRENT_VERSION;
+    pending_change.id = protocol_state.change_nonce;
+    pending_change.change = change;
+    pending_change.proposer = ctx.accounts.admin.key();
+    pending_change.queued_at = clock.unix_timestamp;
+    pending_change.eta = eta;
+    pending_change.bump = ctx.bumps.pending_change;
+
+    protocol_state.change_nonce = protocol_state
+        .change_nonce
+        .checked_add(1)
+        .ok_or(AshbornError::Overflow)?;
+
+    emit_cpi!(ChangeQueued {
+        id: pending_change.id,
+        change,
+        proposer: pending_change.proposer,
+        eta,
+    });
+
+    msg!("Change #{} queued: {:?}, eta {}", pending_change.id, change, eta);
+
+    Ok(())
+}
+
+/// Apply a queued change once its timelock has elapsed
+pub fn execute_handler(ctx: Context<ExecuteChange>) -> Result<()> {
+    let clock = Clock::get()?;
+    let pending_change = &ctx.accounts.pending_change;
+
+    pending_change.check_executable(clock.unix_timestamp)?;
+
+    // Re-validate: constants such as the fee ceiling may have changed in an upgrade
+    let change = pending_change.change;
+    change.validate()?;
+
+    let protocol_state = &mut ctx.accounts.protocol_state;
+    match change {
+        ParameterChange::FeeBps(fee_bps) => {
+            protocol_state.fee_bps = fee_bps;
+        }
+        ParameterChange::MinPrivacyDelay(delay) => {
+            protocol_state.min_privacy_delay = delay;
+        }
+        ParameterChange::DenominationDelay { tier, delay } => {
+            protocol_state.denomination_delays[tier as usize] = delay;
+        }
+        ParameterChange::RateLimit {
+            max_deposits_per_epoch,
+            epoch_duration_slots,
+        } => {
+            ctx.accounts
+                .rate_limit
+                .update_config(max_deposits_per_epoch, epoch_duration_slots, clock.slot)?;
+        }
+        ParameterChange::ActivateVerifyingKey { circuit, version } => {
+            let (Some(registry), Some(key)) =
+                (ctx.accounts.registry.as_mut(), ctx.accounts.verifying_key.as_mut())
+            else {
+                return err!(AshbornError::InvalidVerifyingKey);
+            };
+            require!(
+                key.circuit == circuit && key.version == version,
+                AshbornError::InvalidVerifyingKey
+            );
+
+            let previous = ctx.accounts.previous_key.as_mut().map(|previous| &mut ***previous);
+            if let Some(retired) = registry.activate(key, previous)? {
+                msg!("Verifying key {:?} v{} retired", circuit, retired);
+            }
+        }
+    }
+
+    emit_cpi!(ChangeExecuted {
+        id: pending_change.id,
+        change,
+        executed_at: clock.unix_timestamp,
+    });
+
+    msg!("Change #{} executed: {:?}", pending_change.id, change);
+
+    Ok(())
+}
+
+/// Drop a queued change before it is executed
+pub fn cancel_handler(ctx: Context<CancelChange>) -> Result<()> {
+    let pending_change = &ctx.accounts.pending_change;
+
+    emit_cpi!(ChangeCancelled {
+        id: pending_change.id,
+        change: pending_change.change,
+    });
+
+    msg!("Change #{} cancelled", pending_change.id);
+
+    Ok(())
+}
@@ -4,11 +4,14 @@
 //! Merkle tree nullifiers
 
 use anchor_lang::prelude::*;
-use crate::state::{ShadowVault, ShieldedNote, CommitmentTree, Nullifier};
+use crate::state::{ShadowVault, ShieldedNote, CommitmentTree, load_tree, load_tree_mut, NullifierSet, PoolConfig, ProtocolState, CircuitId, VerifyingKey};
 use crate::errors::AshbornError;
+use crate::events::{NoteCreated, NullifierSpent};
 use crate::zk::verify_transfer_proof;
+use super::rollover;
 
 /// Accounts for shadow transfer
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(nullifier_hash: [u8; 32])]
 pub struct ShadowTransfer<'info> {
@@ -18,6 +21,7 @@ pub struct ShadowTransfer<'info> {
         seeds = [b"shadow_vault", sender.key().as_ref()],
         bump = sender_vault.bump,
         has_one = owner @ AshbornError::Unauthorized,
+        constraint = sender_vault.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub sender_vault: Box<Account<'info, ShadowVault>>,
 
@@ -27,26 +31,83 @@ pub struct ShadowTransfer<'info> {
         seeds = [b"shielded_note", sender_vault.key().as_ref(), &source_note.index.to_le_bytes()],
         bump = source_note.bump,
         constraint = !source_note.spent @ AshbornError::NoteAlreadySpent,
+        constraint = source_note.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub source_note: Box<Account<'info, ShieldedNote>>,
 
-    /// Nullifier PDA (Proves spentness)
+    /// Nullifier set shard for this nullifier (double-spend check)
+    #[account(
+        mut,
+        seeds = [b"nullifier_set".as_ref(), &nullifier_hash[31..]],
+        bump = nullifier_set.load()?.bump,
+        constraint = nullifier_set.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub nullifier_set: AccountLoader<'info, NullifierSet>,
+
+    /// Legacy nullifier PDA, must not exist (spent before the nullifier sets)
+    /// CHECK: PDA verified by seeds, only checked for emptiness
     #[account(
-        init,
-        payer = sender,
-        space = Nullifier::SIZE,
         seeds = [b"nullifier", nullifier_hash.as_ref()],
-        bump
+        bump,
+        constraint = legacy_nullifier.data_is_empty() @ AshbornError::NullifierAlreadyUsed,
+    )]
+    pub legacy_nullifier: UncheckedAccount<'info>,
+
+    /// Protocol state (pause switch, tx accounting)
+    #[account(
+        mut,
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        constraint = !protocol_state.paused @ AshbornError::ProtocolPaused,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Active verifying key for the transfer circuit
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[CircuitId::Transfer as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
     )]
-    pub nullifier: Box<Account<'info, Nullifier>>,
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
 
-    /// Global commitment tree
+    /// Pool configuration for the note's mint (tracks the active tree shard)
     #[account(
         mut,
-        seeds = [b"commitment_tree"],
-        bump = commitment_tree.bump,
+        seeds = [b"pool_config", source_note.mint.as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
     )]
-    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Commitment tree shard holding the note's leaf (the proof's Merkle root is one of its roots)
+    #[account(
+        seeds = [b"commitment_tree", source_note.mint.as_ref(), &root_tree.load()?.tree_id.to_le_bytes()],
+        bump = root_tree.load()?.bump,
+        constraint = root_tree.load()?.mint == source_note.mint @ AshbornError::InvalidMint,
+        constraint = root_tree.load()?.tree_id == CommitmentTree::shard_of(source_note.leaf_index) @ AshbornError::InvalidMerkleRoot,
+        constraint = root_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub root_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Active commitment tree shard of the note's mint (receives the change commitment)
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", source_note.mint.as_ref(), &commitment_tree.load()?.tree_id.to_le_bytes()],
+        bump = commitment_tree.load()?.bump,
+        constraint = commitment_tree.key() == pool_config.commitment_tree @ AshbornError::InactiveCommitmentTree,
+        constraint = commitment_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub commitment_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Next commitment tree shard, only created if the active one is full
+    /// CHECK: PDA verified by seeds, created and initialized on rollover
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", source_note.mint.as_ref(), &(commitment_tree.load()?.tree_id + 1).to_le_bytes()],
+        bump,
+    )]
+    pub next_commitment_tree: UncheckedAccount<'info>,
 
     /// The change note created for sender
     #[account(
@@ -75,30 +136,33 @@ pub fn handler(
     output_commitment: [u8; 32],
     change_commitment: [u8; 32],
     proof: Vec<u8>,
-    merkle_siblings: [[u8; 32]; 20],
     merkle_root: [u8; 32],
 ) -> Result<()> {
     let source_note = &ctx.accounts.source_note;
     // let nullifier_tree = &mut ctx.accounts.nullifier_tree; // Removed
-    let commitment_tree = &mut ctx.accounts.commitment_tree;
     let sender_vault = &mut ctx.accounts.sender_vault;
     let change_note = &mut ctx.accounts.change_note;
-    let nullifier_account = &mut ctx.accounts.nullifier;
+    let protocol_state = &mut ctx.accounts.protocol_state;
     let clock = Clock::get()?;
 
-    // 1. Verify Merkle root is valid (current or recent)
-    require!(
-        commitment_tree.is_valid_root(&merkle_root),
-        AshbornError::InvalidMerkleRoot
-    );
-
-    // 2. Initialize Nullifier PDA (Double Spend Check is implicit via 'init')
-    nullifier_account.hash = nullifier;
-    nullifier_account.spent_at = clock.unix_timestamp;
-    nullifier_account.bump = ctx.bumps.nullifier;
+    // 1. Verify Merkle root is valid (current or recent root of its shard)
+    {
+        let (root_tree, root_history) = load_tree(&ctx.accounts.root_tree)?;
+        require!(
+            root_tree.is_valid_root(&merkle_root, &root_history),
+            AshbornError::InvalidMerkleRoot
+        );
+    }
+
+    // 2. Record the nullifier (fails on double spend)
+    {
+        let (mut nullifier_set, mut slots) = load_tree_mut(&ctx.accounts.nullifier_set)?;
+        nullifier_set.insert(nullifier, &mut slots)?;
+    }
 
     // 3. REAL Groth16 proof verification 
     let proof_valid = verify_transfer_proof(
+        &ctx.accounts.verifying_key,
         &proof,
         &source_note.commitment,
         &nullifier,
@@ -112,21 +176,48 @@ pub fn handler(
     // 4. Mark source note as spent (redundant with nullifier but good for vault state)
     ctx.accounts.source_note.spent = true;
 
-    // 5. Insert new commitments into commitment tree
-    let change_index = commitment_tree.insert_commitment(change_commitment, &merkle_siblings)?;
+    // 5. Insert new commitments into the active commitment tree shard
+    let inserted = rollover::append_commitment(
+        &mut ctx.accounts.pool_config,
+        &ctx.accounts.commitment_tree,
+        &ctx.accounts.next_commitment_tree,
+        ctx.bumps.next_commitment_tree,
+        &ctx.accounts.sender,
+        &ctx.accounts.system_program,
+        change_commitment,
+    )?;
 
     // 6. Create change note
+    change_note.version = ShieldedNote::CURRENT_VERSION;
     change_note.vault = sender_vault.key();
+    change_note.mint = ctx.accounts.source_note.mint;
     change_note.commitment = change_commitment;
-    change_note.index = change_index;
+    change_note.index = sender_vault.note_count + 1;
+    change_note.leaf_index = inserted.index;
+    change_note.denomination_tier = ShieldedNote::NO_DENOMINATION;
     change_note.spent = false;
     change_note.created_at = clock.unix_timestamp;
-    change_note.unshield_after = clock.unix_timestamp + 24 * 60 * 60; // 24h delay
+    change_note.unshield_after = protocol_state.unshield_after(clock.unix_timestamp, None)?;
     change_note.bump = ctx.bumps.change_note;
 
     // 7. Update vault state
     sender_vault.note_count += 1;
     sender_vault.last_activity = clock.unix_timestamp;
+    protocol_state.record_tx()?;
+
+    emit_cpi!(NullifierSpent {
+        nullifier,
+        spent_at: clock.unix_timestamp,
+    });
+    emit_cpi!(inserted);
+    emit_cpi!(NoteCreated {
+        note: change_note.key(),
+        vault: change_note.vault,
+        commitment: change_commitment,
+        leaf_index: change_note.leaf_index,
+        denomination_tier: change_note.denomination_tier,
+        unshield_after: change_note.unshield_after,
+    });
 
     msg!("Shadow transfer executed successfully");
     msg!("Nullifier recorded: {:?}", &nullifier[..8]);
@@ -1,14 +1,20 @@
 //! Updated Unshield Instruction with Privacy Delay
 //!
-//! privacy-preserving: 24-hour minimum delay before unshield
+//! privacy-preserving: minimum delay before unshield
+//! Outflow circuit breaker caps how fast value can leave the pool
 
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use crate::state::{ShadowVault, ShieldedNote, Nullifier, ProtocolState};
+use crate::state::{
+    load_tree, load_tree_mut, CircuitId, CommitmentTree, NullifierSet, OutflowLimit, PoolConfig,
+    ProtocolState, ShadowVault, ShieldedNote, VerifyingKey,
+};
 use crate::errors::AshbornError;
-use crate::zk::verify_transfer_proof;
+use crate::events::{ConfigChange, NullifierSpent, ProtocolConfigChanged};
+use crate::zk::verify_withdraw_proof;
 
 /// Accounts for unshield
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(amount: u64, nullifier_hash: [u8; 32])]
 pub struct Unshield<'info> {
@@ -18,6 +24,7 @@ pub struct Unshield<'info> {
         seeds = [b"shadow_vault", owner.key().as_ref()],
         bump = vault.bump,
         has_one = owner @ AshbornError::Unauthorized,
+        constraint = vault.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub vault: Box<Account<'info, ShadowVault>>,
 
@@ -27,38 +34,102 @@ pub struct Unshield<'info> {
         seeds = [b"shielded_note", vault.key().as_ref(), &source_note.index.to_le_bytes()],
         bump = source_note.bump,
         constraint = !source_note.spent @ AshbornError::NoteAlreadySpent,
+        constraint = source_note.is_current() @ AshbornError::AccountNotMigrated,
+        constraint = source_note.mint == pool_token_account.mint @ AshbornError::InvalidMint,
     )]
     pub source_note: Box<Account<'info, ShieldedNote>>,
 
-    /// Nullifier PDA (Proves spentness)
+    /// Nullifier set shard for this nullifier (double-spend check)
+    #[account(
+        mut,
+        seeds = [b"nullifier_set".as_ref(), &nullifier_hash[31..]],
+        bump = nullifier_set.load()?.bump,
+        constraint = nullifier_set.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub nullifier_set: AccountLoader<'info, NullifierSet>,
+
+    /// Legacy nullifier PDA, must not exist (spent before the nullifier sets)
+    /// CHECK: PDA verified by seeds, only checked for emptiness
     #[account(
-        init,
-        payer = owner,
-        space = Nullifier::SIZE,
         seeds = [b"nullifier", nullifier_hash.as_ref()],
-        bump
+        bump,
+        constraint = legacy_nullifier.data_is_empty() @ AshbornError::NullifierAlreadyUsed,
     )]
-    pub nullifier_account: Box<Account<'info, Nullifier>>,
+    pub legacy_nullifier: UncheckedAccount<'info>,
 
-    /// Protocol state for fee recipient
+    /// Protocol state for fees, pause switch and TVL accounting
     #[account(
+        mut,
         seeds = [b"protocol_state"],
         bump = protocol_state.bump,
+        constraint = !protocol_state.paused @ AshbornError::ProtocolPaused,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
     )]
     pub protocol_state: Box<Account<'info, ProtocolState>>,
 
-    /// Shielded pool token account
-    #[account(mut)]
+    /// Active verifying key for the transfer circuit
+    #[account(
+        seeds = [b"verifying_key".as_ref(), &[CircuitId::Transfer as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Commitment tree shard holding the note's leaf (the proof's Merkle root is one of its roots)
+    #[account(
+        seeds = [b"commitment_tree", source_note.mint.as_ref(), &root_tree.load()?.tree_id.to_le_bytes()],
+        bump = root_tree.load()?.bump,
+        constraint = root_tree.load()?.mint == source_note.mint @ AshbornError::InvalidMint,
+        constraint = root_tree.load()?.tree_id == CommitmentTree::shard_of(source_note.leaf_index) @ AshbornError::InvalidMerkleRoot,
+        constraint = root_tree.load()?.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub root_tree: AccountLoader<'info, CommitmentTree>,
+
+    /// Shielded pool token account (PDA validated!)
+    #[account(
+        mut,
+        seeds = [b"shielded_pool", pool_token_account.mint.as_ref()],
+        bump,
+    )]
     pub pool_token_account: Box<Account<'info, TokenAccount>>,
 
+    /// Fee vault for this pool's mint (protocol revenue, kept out of the pool)
+    #[account(
+        mut,
+        seeds = [b"fee_vault", pool_token_account.mint.as_ref()],
+        bump,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Pool configuration (solvency accounting)
+    #[account(
+        mut,
+        seeds = [b"pool_config", pool_token_account.mint.as_ref()],
+        bump = pool_config.bump,
+        constraint = pool_config.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    /// Outflow circuit breaker for this pool's mint
+    #[account(
+        mut,
+        seeds = [b"outflow_limit", pool_token_account.mint.as_ref()],
+        bump = outflow_limit.bump,
+        constraint = outflow_limit.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub outflow_limit: Box<Account<'info, OutflowLimit>>,
+
     /// User's destination token account
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_token_account.mint == pool_token_account.mint @ AshbornError::InvalidMint,
+    )]
     pub user_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// Pool authority PDA
+    /// Pool authority PDA for this pool's mint
     /// CHECK: PDA authority for pool
     #[account(
-        seeds = [b"pool_authority"],
+        seeds = [b"pool_authority", pool_token_account.mint.as_ref()],
         bump,
     )]
     pub pool_authority: AccountInfo<'info>,
@@ -77,54 +148,97 @@ pub fn handler(
     amount: u64,
     nullifier: [u8; 32],
     proof: Vec<u8>,
-    merkle_siblings: [[u8; 32]; 20],
+    merkle_root: [u8; 32],
 ) -> Result<()> {
-    let vault = &mut ctx.accounts.vault;
-    let source_note = &mut ctx.accounts.source_note;
-    let nullifier_account = &mut ctx.accounts.nullifier_account;
-    let protocol_state = &ctx.accounts.protocol_state;
     let clock = Clock::get()?;
 
-    // 1. Privacy delay check (privacy-preserving: must wait 24h)
+    // 1. Privacy delay check (privacy-preserving: must wait out the delay)
     require!(
-        clock.unix_timestamp >= source_note.unshield_after,
+        clock.unix_timestamp >= ctx.accounts.source_note.unshield_after,
         AshbornError::TooSoonToUnshield
     );
 
-    let time_waited = clock.unix_timestamp - source_note.created_at;
+    let time_waited = clock.unix_timestamp - ctx.accounts.source_note.created_at;
     msg!("Privacy delay satisfied: {} seconds elapsed", time_waited);
 
-    // 2. Initialize Nullifier PDA (Double Spend Check is implicit)
-    nullifier_account.hash = nullifier;
-    nullifier_account.spent_at = clock.unix_timestamp;
-    nullifier_account.bump = ctx.bumps.nullifier_account;
+    // 2. Verify Merkle root is valid (current or recent root of its shard)
+    {
+        let (root_tree, root_history) = load_tree(&ctx.accounts.root_tree)?;
+        require!(
+            root_tree.is_valid_root(&merkle_root, &root_history),
+            AshbornError::InvalidMerkleRoot
+        );
+    }
 
-    // 3. Verify withdrawal proof
-    // For unshield, we verify the user knows the preimage of the commitment
-    let proof_valid = verify_transfer_proof(
+    // 3. The proof binds the amount: the note's committed value must be exactly `amount`
+    let amount = ctx.accounts.source_note.withdrawal_amount(amount)?;
+    let proof_valid = verify_withdraw_proof(
+        &ctx.accounts.verifying_key,
         &proof,
-        &source_note.commitment,
+        &ctx.accounts.source_note.commitment,
         &nullifier,
-        &[0u8; 32], // No output commitment for unshield
-        &[0u8; 32], // No change for full unshield
-        &[0u8; 32], // Merkle root
+        amount,
+        &merkle_root,
     )?;
     require!(proof_valid, AshbornError::InvalidWithdrawProof);
 
-    // 4. Mark note as spent
+    // 4. Outflow circuit breaker: an over-cap withdrawal trips it and pays nothing.
+    // Returning Ok keeps the trip; the note stays unspent.
+    let outflow_limit = &mut ctx.accounts.outflow_limit;
+    if !outflow_limit.record_withdrawal(amount, clock.slot, clock.unix_timestamp)? {
+        emit_cpi!(ProtocolConfigChanged {
+            authority: ctx.accounts.owner.key(),
+            change: ConfigChange::OutflowTripped {
+                mint: outflow_limit.mint,
+            },
+        });
+        msg!("Outflow limit exceeded; breaker for {} tripped", outflow_limit.mint);
+        return Ok(());
+    }
+
+    let vault = &mut ctx.accounts.vault;
+    let source_note = &mut ctx.accounts.source_note;
+    let protocol_state = &mut ctx.accounts.protocol_state;
+
+    // 5. Record the nullifier (fails on double spend)
+    {
+        let (mut nullifier_set, mut slots) = load_tree_mut(&ctx.accounts.nullifier_set)?;
+        nullifier_set.insert(nullifier, &mut slots)?;
+    }
+
+    // 6. Mark note as spent
     source_note.spent = true;
 
-    // 5. Calculate fee with overflow protection
+    // 7. Calculate fee with overflow protection
     let fee = amount
         .checked_mul(protocol_state.fee_bps as u64)
         .and_then(|v| v.checked_div(10000))
         .ok_or(AshbornError::Overflow)?;
     let net_amount = amount.saturating_sub(fee);
 
-    // 6. Transfer tokens from pool to user
-    let pool_seeds = &[b"pool_authority".as_ref(), &[ctx.bumps.pool_authority]];
+    // 8. Solvency invariant, then transfer: fee to the fee vault, the rest to the user
+    ctx.accounts
+        .pool_config
+        .check_solvency(ctx.accounts.pool_token_account.amount, amount)?;
+    ctx.accounts.pool_config.record_withdrawal(amount)?;
+
+    let mint = ctx.accounts.pool_token_account.mint;
+    let pool_seeds = &[b"pool_authority".as_ref(), mint.as_ref(), &[ctx.bumps.pool_authority]];
     let signer_seeds = &[&pool_seeds[..]];
 
+    if fee > 0 {
+        let fee_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.pool_token_account.to_account_info(),
+                to: ctx.accounts.fee_vault.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(fee_ctx, fee)?;
+    }
+
     let transfer_ctx = CpiContext::new_with_signer(
         ctx.accounts.token_program.to_account_info(),
         Transfer {
@@ -136,10 +250,19 @@ pub fn handler(
     );
     token::transfer(transfer_ctx, net_amount)?;
 
-    // 7. Update vault state (no balance stored - privacy!)
-    vault.note_count = vault.note_count.saturating_sub(1);
+    // 9. Protocol accounting: the full amount leaves the shielded set
+    protocol_state.record_unshield(amount)?;
+
+    // 10. Update vault state (no balance stored - privacy!). `note_count` is the
+    // note sequence counter, so it never goes down: a reused number would collide
+    // with a live note's PDA.
     vault.last_activity = clock.unix_timestamp;
 
+    emit_cpi!(NullifierSpent {
+        nullifier,
+        spent_at: clock.unix_timestamp,
+    });
+
     msg!("Assets unshielded successfully");
     msg!("Amount: {}, Fee: {}, Net: {}", amount, fee, net_amount);
     msg!("Nullifier recorded: {:?}", &nullifier[..8]);
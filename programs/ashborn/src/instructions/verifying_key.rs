@@ -0,0 +1,211 @@
+//! Verifying key management (admin only)
+//!
+//! Keys are created with their curve points and the first IC chunk, filled
+//! up with further chunks, then activated through the timelock
+//! (`ParameterChange::ActivateVerifyingKey`). Activation is the only way a
+//! key becomes usable and retires the circuit's previous key in the same
+//! step, so each circuit has at most one active key. Retiring is immediate,
+//! so a broken key can be pulled without waiting out the timelock.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+use crate::events::{ConfigChange, ProtocolConfigChanged};
+use crate::state::{
+    CircuitId, ProtocolState, VerifyingKey, VerifyingKeyRegistry, VerifyingKeyStatus,
+};
+
+/// Accounts for creating a verifying key
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(circuit: CircuitId, version: u32)]
+pub struct CreateVerifyingKey<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// The new key, sized for the circuit's full IC array
+    #[account(
+        init,
+        payer = admin,
+        space = VerifyingKey::space(circuit),
+        seeds = [b"verifying_key".as_ref(), &[circuit as u8], &version.to_le_bytes()],
+        bump,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Active version per circuit (created with the first key)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = VerifyingKeyRegistry::SIZE,
+        seeds = [b"vk_registry"],
+        bump,
+    )]
+    pub registry: Box<Account<'info, VerifyingKeyRegistry>>,
+
+    /// Protocol admin (payer)
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for uploading IC points to a key
+#[derive(Accounts)]
+pub struct AppendVerifyingKeyIc<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Key being uploaded
+    #[account(
+        mut,
+        seeds = [b"verifying_key".as_ref(), &[verifying_key.circuit as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for retiring a key
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RetireVerifyingKey<'info> {
+    /// Global protocol configuration
+    #[account(
+        seeds = [b"protocol_state"],
+        bump = protocol_state.bump,
+        has_one = admin @ AshbornError::InvalidAdmin,
+        constraint = protocol_state.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub protocol_state: Box<Account<'info, ProtocolState>>,
+
+    /// Active version per circuit
+    #[account(
+        mut,
+        seeds = [b"vk_registry"],
+        bump = registry.bump,
+        constraint = registry.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub registry: Box<Account<'info, VerifyingKeyRegistry>>,
+
+    /// Key being retired
+    #[account(
+        mut,
+        seeds = [b"verifying_key".as_ref(), &[verifying_key.circuit as u8], &verifying_key.version.to_le_bytes()],
+        bump = verifying_key.bump,
+        constraint = verifying_key.is_current() @ AshbornError::AccountNotMigrated,
+    )]
+    pub verifying_key: Box<Account<'info, VerifyingKey>>,
+
+    /// Protocol admin
+    pub admin: Signer<'info>,
+}
+
+/// Create a key for `circuit` at `version` with its curve points and first IC chunk
+#[allow(clippy::too_many_arguments)]
+pub fn create_handler(
+    ctx: Context<CreateVerifyingKey>,
+    circuit: CircuitId,
+    version: u32,
+    alpha_g1: [u8; 64],
+    beta_g2: [u8; 128],
+    gamma_g2: [u8; 128],
+    delta_g2: [u8; 128],
+    ic: Vec<[u8; 64]>,
+) -> Result<()> {
+    // Version 0 means "no active key" in the registry
+    require!(version > 0, AshbornError::InvalidVerifyingKey);
+
+    // init_if_needed leaves a new registry zeroed; an existing one must be current
+    let registry = &mut ctx.accounts.registry;
+    if registry.version == 0 {
+        registry.version = VerifyingKeyRegistry::CURRENT_VERSION;
+        registry.bump = ctx.bumps.registry;
+    }
+    require!(registry.is_current(), AshbornError::AccountNotMigrated);
+
+    let verifying_key = &mut ctx.accounts.verifying_key;
+    verifying_key.layout_version = VerifyingKey::CURRENT_LAYOUT_VERSION;
+    verifying_key.circuit = circuit;
+    verifying_key.version = version;
+    verifying_key.status = VerifyingKeyStatus::Uploading;
+    verifying_key.alpha_g1 = alpha_g1;
+    verifying_key.beta_g2 = beta_g2;
+    verifying_key.gamma_g2 = gamma_g2;
+    verifying_key.delta_g2 = delta_g2;
+    verifying_key.bump = ctx.bumps.verifying_key;
+    verifying_key.ic = Vec::with_capacity(circuit.n_public() + 1);
+    if !ic.is_empty() {
+        verifying_key.append_ic(&ic)?;
+    }
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::VerifyingKeyCreated { circuit, version },
+    });
+
+    msg!("Verifying key {:?} v{} created", circuit, version);
+    msg!("IC points: {}/{}", verifying_key.ic.len(), circuit.n_public() + 1);
+
+    Ok(())
+}
+
+/// Append a chunk of IC points
+pub fn append_ic_handler(ctx: Context<AppendVerifyingKeyIc>, ic: Vec<[u8; 64]>) -> Result<()> {
+    let verifying_key = &mut ctx.accounts.verifying_key;
+    verifying_key.append_ic(&ic)?;
+
+    msg!(
+        "Verifying key {:?} v{}: {}/{} IC points",
+        verifying_key.circuit,
+        verifying_key.version,
+        verifying_key.ic.len(),
+        verifying_key.circuit.n_public() + 1
+    );
+
+    Ok(())
+}
+
+/// Withdraw a key; retiring the active one leaves its circuit without a key
+pub fn retire_handler(ctx: Context<RetireVerifyingKey>) -> Result<()> {
+    let verifying_key = &mut ctx.accounts.verifying_key;
+    require!(
+        verifying_key.status != VerifyingKeyStatus::Retired,
+        AshbornError::VerifyingKeyLocked
+    );
+
+    let circuit = verifying_key.circuit;
+    let registry = &mut ctx.accounts.registry;
+    if registry.active_version(circuit) == Some(verifying_key.version) {
+        registry.active_versions[circuit as usize] = 0;
+        msg!("WARNING: {:?} proofs are rejected until a new key is activated", circuit);
+    }
+    verifying_key.status = VerifyingKeyStatus::Retired;
+
+    emit_cpi!(ProtocolConfigChanged {
+        authority: ctx.accounts.admin.key(),
+        change: ConfigChange::VerifyingKeyRetired {
+            circuit,
+            version: verifying_key.version,
+        },
+    });
+
+    msg!("Verifying key {:?} v{} retired", circuit, verifying_key.version);
+
+    Ok(())
+}
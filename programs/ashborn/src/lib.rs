@@ -13,6 +13,7 @@
 use anchor_lang::prelude::*;
 
 pub mod errors;
+pub mod events;
 pub mod instructions;
 pub mod state;
 pub mod zk;
@@ -21,9 +22,17 @@ pub mod zk;
 mod tests;
 
 use instructions::*;
+use state::{CircuitId, ParameterChange};
 
+#[cfg(not(feature = "demo"))]
 declare_id!("BzBUgtEFiJjUXR2xjsvhvVx2oZEhD2K6qenpg727z5Qe");
 
+// Demo builds accept any well-formed proof, so they get their own devnet
+// program id: deployed at the production address, every instruction fails
+// Anchor's program id check
+#[cfg(feature = "demo")]
+declare_id!("AMJ7hCChY9hQrpi2Wr9gyiLm1AF6qSJwnwyFbCwYFd8t");
+
 /// The Shadow Monarch program - orchestrates all privacy operations
 #[program]
 pub mod ashborn {
@@ -39,16 +48,187 @@ pub mod ashborn {
         instructions::initialize::handler(ctx)
     }
 
-    /// Shield assets (SDK-compatible simplified version)
+    /// Set the view key hash that ownership proofs bind to (vault owner)
+    pub fn set_view_key_hash(ctx: Context<SetViewKey>, view_key_hash: [u8; 32]) -> Result<()> {
+        instructions::initialize::set_view_key_handler(ctx, view_key_hash)
+    }
+
+    /// Initialize the protocol - the global state every pool depends on
     /// 
-    /// Creates a shielded note with commitment. Demo-friendly version
-    /// that doesn't require full Merkle tree proofs.
-    pub fn shield(
-        ctx: Context<Shield>,
-        amount: u64,
-        commitment: [u8; 32],
+    /// Creates in one flow:
+    /// - ProtocolState (admin, fees, privacy delay)
+    /// - RateLimitState for deposit protection
+    pub fn initialize_protocol(
+        ctx: Context<InitializeProtocol>,
+        fee_bps: u16,
+        fee_recipient: Pubkey,
+        min_privacy_delay: i64,
     ) -> Result<()> {
-        instructions::shield::shield_simple_handler(ctx, amount, commitment)
+        instructions::initialize_protocol::handler(ctx, fee_bps, fee_recipient, min_privacy_delay)
+    }
+
+    /// Set the account that receives protocol fees (admin only)
+    pub fn set_fee_recipient(ctx: Context<AdminConfig>, fee_recipient: Pubkey) -> Result<()> {
+        instructions::admin::set_fee_recipient_handler(ctx, fee_recipient)
+    }
+
+    /// Propose a new admin (admin only, two-step handover)
+    pub fn propose_admin(ctx: Context<AdminConfig>, new_admin: Pubkey) -> Result<()> {
+        instructions::admin::propose_admin_handler(ctx, new_admin)
+    }
+
+    /// Accept a pending admin handover (signed by the proposed admin)
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        instructions::admin::accept_admin_handler(ctx)
+    }
+
+    /// Set the emergency guardian key (admin only)
+    pub fn set_guardian(ctx: Context<AdminConfig>, guardian: Pubkey) -> Result<()> {
+        instructions::admin::set_guardian_handler(ctx, guardian)
+    }
+
+    /// Pause every state-changing instruction (admin or guardian)
+    pub fn pause(ctx: Context<PauseProtocol>) -> Result<()> {
+        instructions::admin::pause_handler(ctx)
+    }
+
+    /// Resume normal operations (admin only)
+    pub fn unpause(ctx: Context<AdminConfig>) -> Result<()> {
+        instructions::admin::unpause_handler(ctx)
+    }
+
+    /// Queue a fee, privacy delay (global or per-tier), rate limit or
+    /// verifying key activation behind the timelock (admin only)
+    pub fn queue_change(
+        ctx: Context<QueueChange>,
+        change: ParameterChange,
+        eta: i64,
+    ) -> Result<()> {
+        instructions::timelock::queue_handler(ctx, change, eta)
+    }
+
+    /// Apply a queued change once its timelock has elapsed (anyone)
+    pub fn execute_change(ctx: Context<ExecuteChange>) -> Result<()> {
+        instructions::timelock::execute_handler(ctx)
+    }
+
+    /// Cancel a queued change (admin only)
+    pub fn cancel_change(ctx: Context<CancelChange>) -> Result<()> {
+        instructions::timelock::cancel_handler(ctx)
+    }
+
+    /// Create the shielded pool, fee vault, pool config and first commitment
+    /// tree shard for a mint (admin only)
+    pub fn initialize_pool(
+        ctx: Context<InitializePool>,
+        max_tvl: u64,
+        root_history_len: u32,
+    ) -> Result<()> {
+        instructions::pool::handler(ctx, max_tvl, root_history_len)
+    }
+
+    /// Update a pool's TVL cap (admin only)
+    pub fn set_pool_cap(ctx: Context<SetPoolCap>, max_tvl: u64) -> Result<()> {
+        instructions::pool::set_cap_handler(ctx, max_tvl)
+    }
+
+    /// Add root history slots to a commitment tree shard (admin only)
+    pub fn grow_root_history(ctx: Context<GrowRootHistory>, additional: u32) -> Result<()> {
+        instructions::pool::grow_root_history_handler(ctx, additional)
+    }
+
+    /// Withdraw accrued unshield fees to the fee recipient (admin only)
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        instructions::fees::withdraw_handler(ctx, amount)
+    }
+
+    /// Create or update the per-epoch withdrawal cap for a mint (admin only)
+    pub fn set_outflow_limit(
+        ctx: Context<SetOutflowLimit>,
+        max_outflow_per_epoch: u64,
+        epoch_duration_slots: u64,
+    ) -> Result<()> {
+        instructions::circuit_breaker::set_handler(ctx, max_outflow_per_epoch, epoch_duration_slots)
+    }
+
+    /// Halt withdrawals for a mint via its outflow circuit breaker (admin or guardian)
+    pub fn trip_outflow_limit(ctx: Context<TripOutflowLimit>) -> Result<()> {
+        instructions::circuit_breaker::trip_handler(ctx)
+    }
+
+    /// Re-arm a tripped outflow circuit breaker (admin or guardian)
+    pub fn reset_outflow_limit(ctx: Context<ResetOutflowLimit>) -> Result<()> {
+        instructions::circuit_breaker::reset_handler(ctx)
+    }
+
+    /// Upgrade a pre-versioning vault to the current layout (vault owner)
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        instructions::migrate::vault_handler(ctx)
+    }
+
+    /// Upgrade an older note to the current layout
+    /// (vault owner, admin attests the mint)
+    ///
+    /// `seq` is the vault's note counter the note was created at (its PDA seed).
+    pub fn migrate_note(ctx: Context<MigrateNote>, seq: u32) -> Result<()> {
+        instructions::migrate::note_handler(ctx, seq)
+    }
+
+    /// Move a pool onto its own commitment tree and pool authority (admin only)
+    pub fn migrate_pool(ctx: Context<MigratePool>, root_history_len: u32) -> Result<()> {
+        instructions::migrate::pool_handler(ctx, root_history_len)
+    }
+
+    /// Upgrade an older protocol state to the current layout (admin only)
+    pub fn migrate_protocol_state(ctx: Context<MigrateProtocolState>) -> Result<()> {
+        instructions::migrate::protocol_state_handler(ctx)
+    }
+
+    /// Drop the changelog from a v1 commitment tree shard (admin only)
+    pub fn migrate_commitment_tree(ctx: Context<MigrateCommitmentTree>) -> Result<()> {
+        instructions::migrate::commitment_tree_handler(ctx)
+    }
+
+    /// Import a legacy nullifier PDA into its nullifier set and reclaim its rent (admin only)
+    pub fn migrate_nullifier(ctx: Context<MigrateNullifier>, nullifier_hash: [u8; 32]) -> Result<()> {
+        instructions::migrate::nullifier_handler(ctx, nullifier_hash)
+    }
+
+    /// Create the nullifier set shard for nullifiers ending in `prefix` (admin only)
+    pub fn initialize_nullifier_set(ctx: Context<InitializeNullifierSet>, prefix: u8) -> Result<()> {
+        instructions::nullifier::initialize_set_handler(ctx, prefix)
+    }
+
+    /// Add empty slots to a full nullifier set shard (permissionless, caller pays rent)
+    pub fn grow_nullifier_set(ctx: Context<GrowNullifierSet>) -> Result<()> {
+        instructions::nullifier::grow_set_handler(ctx)
+    }
+
+    /// Create a verifying key for one circuit version with its first IC chunk (admin only)
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_verifying_key(
+        ctx: Context<CreateVerifyingKey>,
+        circuit: CircuitId,
+        version: u32,
+        alpha_g1: [u8; 64],
+        beta_g2: [u8; 128],
+        gamma_g2: [u8; 128],
+        delta_g2: [u8; 128],
+        ic: Vec<[u8; 64]>,
+    ) -> Result<()> {
+        instructions::verifying_key::create_handler(
+            ctx, circuit, version, alpha_g1, beta_g2, gamma_g2, delta_g2, ic,
+        )
+    }
+
+    /// Upload the next chunk of a verifying key's IC points (admin only)
+    pub fn append_verifying_key_ic(ctx: Context<AppendVerifyingKeyIc>, ic: Vec<[u8; 64]>) -> Result<()> {
+        instructions::verifying_key::append_ic_handler(ctx, ic)
+    }
+
+    /// Retire a verifying key (admin only)
+    pub fn retire_verifying_key(ctx: Context<RetireVerifyingKey>) -> Result<()> {
+        instructions::verifying_key::retire_handler(ctx)
     }
 
     /// Shield assets into the privacy pool (full version with proofs)
@@ -60,9 +240,21 @@ pub mod ashborn {
         amount: u64,
         commitment: [u8; 32],
         proof: Vec<u8>,
-        merkle_siblings: [[u8; 32]; 20],
     ) -> Result<()> {
-        instructions::shield::handler(ctx, amount, commitment, proof, merkle_siblings)
+        instructions::shield::handler(ctx, amount, commitment, proof)
+    }
+
+    /// Shield several notes of one denomination in a single transaction
+    ///
+    /// One token transfer and one commitment tree root update for the whole
+    /// batch; the new note PDAs are passed as remaining accounts.
+    pub fn shield_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, ShieldBatch<'info>>,
+        amount: u64,
+        commitments: Vec<[u8; 32]>,
+        proofs: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        instructions::shield::batch_handler(ctx, amount, commitments, proofs)
     }
 
     /// Execute a shadow transfer - unlinkable P2P payment
@@ -74,7 +266,6 @@ pub mod ashborn {
         output_commitment: [u8; 32],
         change_commitment: [u8; 32],
         proof: Vec<u8>,
-        merkle_siblings: [[u8; 32]; 20],
         merkle_root: [u8; 32],
     ) -> Result<()> {
         instructions::transfer::handler(
@@ -83,7 +274,6 @@ pub mod ashborn {
             output_commitment,
             change_commitment,
             proof,
-            merkle_siblings,
             merkle_root,
         )
     }
@@ -112,9 +302,9 @@ pub mod ashborn {
         amount: u64,
         nullifier: [u8; 32],
         proof: Vec<u8>,
-        merkle_siblings: [[u8; 32]; 20],
+        merkle_root: [u8; 32],
     ) -> Result<()> {
-        instructions::unshield::handler(ctx, amount, nullifier, proof, merkle_siblings)
+        instructions::unshield::handler(ctx, amount, nullifier, proof, merkle_root)
     }
 }
 
@@ -127,6 +317,7 @@ pub enum ProofType {
     OwnershipProof,
     /// Prove compliance with AML limits
     ComplianceProof,
-    /// Custom proof for specific requirements
+    /// Custom proof for specific requirements (demo builds only, unverified)
+    #[cfg(feature = "demo")]
     Custom,
 }
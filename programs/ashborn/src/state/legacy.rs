@@ -0,0 +1,314 @@
+//! Superseded account layouts
+//!
+//! Kept only so `migrate_*` can read accounts created with an older layout.
+//! Each legacy layout shares its discriminator with the current type and is
+//! identified by the version byte after it. The original unversioned layouts
+//! have no such byte and are identified by their exact data length.
+//! Zero-copy layouts keep their version byte in the header instead.
+
+use anchor_lang::prelude::*;
+
+use crate::state::{CommitmentTree, Denomination, PoolConfig, ProtocolState, ShieldedNote, TREE_DEPTH};
+
+/// ShieldedNote before versioning
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ShieldedNoteV0 {
+    pub vault: Pubkey,
+    pub commitment: [u8; 32],
+    pub encrypted_amount: [u8; 48],
+    pub index: u64,
+    pub denomination_tier: u8,
+    pub spent: bool,
+    pub created_at: i64,
+    pub unshield_after: i64,
+    pub bump: u8,
+}
+
+impl ShieldedNoteV0 {
+    pub const SIZE: usize = ShieldedNoteV1::SIZE - 1; // no version byte
+
+    /// Add the version byte
+    pub fn into_v1(self) -> ShieldedNoteV1 {
+        ShieldedNoteV1 {
+            version: 1,
+            vault: self.vault,
+            commitment: self.commitment,
+            encrypted_amount: self.encrypted_amount,
+            index: self.index,
+            denomination_tier: self.denomination_tier,
+            spent: self.spent,
+            created_at: self.created_at,
+            unshield_after: self.unshield_after,
+            bump: self.bump,
+        }
+    }
+}
+
+/// ShieldedNote v1, before notes recorded their mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ShieldedNoteV1 {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub commitment: [u8; 32],
+    pub encrypted_amount: [u8; 48],
+    pub index: u64,
+    pub denomination_tier: u8,
+    pub spent: bool,
+    pub created_at: i64,
+    pub unshield_after: i64,
+    pub bump: u8,
+}
+
+impl ShieldedNoteV1 {
+    pub const SIZE: usize = ShieldedNoteV2::SIZE - 32; // no mint
+
+    /// Assign the note to `mint`'s pool
+    ///
+    /// `index` still refers to the shared pre-mint tree; the caller moves an
+    /// unspent commitment into the mint's tree and updates it.
+    pub fn into_v2(self, mint: Pubkey) -> ShieldedNoteV2 {
+        ShieldedNoteV2 {
+            version: 2,
+            vault: self.vault,
+            mint,
+            commitment: self.commitment,
+            encrypted_amount: self.encrypted_amount,
+            index: self.index,
+            denomination_tier: self.denomination_tier,
+            spent: self.spent,
+            created_at: self.created_at,
+            unshield_after: self.unshield_after,
+            bump: self.bump,
+        }
+    }
+}
+
+/// ShieldedNote v2, whose `index` held the commitment's leaf, not the PDA seed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ShieldedNoteV2 {
+    pub version: u8,
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub commitment: [u8; 32],
+    pub encrypted_amount: [u8; 48],
+    pub index: u64,
+    pub denomination_tier: u8,
+    pub spent: bool,
+    pub created_at: i64,
+    pub unshield_after: i64,
+    pub bump: u8,
+}
+
+impl ShieldedNoteV2 {
+    pub const SIZE: usize = ShieldedNote::SIZE - 4; // u64 leaf index, no u32 sequence number
+
+    /// Upgrade to the current layout
+    ///
+    /// `seq` is the vault sequence number the note's PDA was derived from;
+    /// the legacy `index` becomes the leaf index.
+    pub fn upgrade(self, seq: u32) -> ShieldedNote {
+        ShieldedNote {
+            version: ShieldedNote::CURRENT_VERSION,
+            vault: self.vault,
+            mint: self.mint,
+            commitment: self.commitment,
+            encrypted_amount: self.encrypted_amount,
+            index: seq,
+            leaf_index: self.index,
+            denomination_tier: self.denomination_tier,
+            spent: self.spent,
+            created_at: self.created_at,
+            unshield_after: self.unshield_after,
+            bump: self.bump,
+        }
+    }
+}
+
+/// PoolConfig before per-mint commitment trees
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolConfigV0 {
+    pub mint: Pubkey,
+    pub max_tvl: u64,
+    pub total_shielded: u64,
+    pub bump: u8,
+}
+
+impl PoolConfigV0 {
+    pub const SIZE: usize = PoolConfig::SIZE - 1 - 32; // no version, no commitment_tree
+
+    /// Upgrade to the current layout with the mint's first tree shard
+    pub fn upgrade(self, commitment_tree: Pubkey) -> PoolConfig {
+        PoolConfig {
+            version: PoolConfig::CURRENT_VERSION,
+            mint: self.mint,
+            max_tvl: self.max_tvl,
+            total_shielded: self.total_shielded,
+            bump: self.bump,
+            commitment_tree,
+        }
+    }
+}
+
+/// ProtocolState before versioning
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProtocolStateV0 {
+    pub admin: Pubkey,
+    pub total_shielded: u64,
+    pub tx_count: u64,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub paused: bool,
+    pub commitment_tree: Pubkey,
+    pub nullifier_tree: Pubkey,
+    pub min_privacy_delay: i64,
+    pub bump: u8,
+}
+
+impl ProtocolStateV0 {
+    pub const SIZE: usize = 8 + // discriminator
+        32 + // admin
+        8 +  // total_shielded
+        8 +  // tx_count
+        2 +  // fee_bps
+        32 + // fee_recipient
+        1 +  // paused
+        32 + // commitment_tree
+        32 + // nullifier_tree
+        8 +  // min_privacy_delay
+        1;   // bump
+
+    /// Add the version byte and the fields added with it; they get safe defaults
+    pub fn into_v1(self) -> ProtocolStateV1 {
+        ProtocolStateV1 {
+            version: 1,
+            admin: self.admin,
+            total_shielded: self.total_shielded,
+            tx_count: self.tx_count,
+            fee_bps: self.fee_bps,
+            fee_recipient: self.fee_recipient,
+            paused: self.paused,
+            commitment_tree: self.commitment_tree,
+            nullifier_tree: self.nullifier_tree,
+            min_privacy_delay: self.min_privacy_delay,
+            bump: self.bump,
+            pending_admin: Pubkey::default(),
+            guardian: self.admin,
+            change_nonce: 0,
+            denomination_delays: [0; Denomination::COUNT],
+        }
+    }
+}
+
+/// ProtocolState v1, with the unused global commitment and nullifier tree keys
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProtocolStateV1 {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub total_shielded: u64,
+    pub tx_count: u64,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub paused: bool,
+    pub commitment_tree: Pubkey,
+    pub nullifier_tree: Pubkey,
+    pub min_privacy_delay: i64,
+    pub bump: u8,
+    pub pending_admin: Pubkey,
+    pub guardian: Pubkey,
+    pub change_nonce: u64,
+    pub denomination_delays: [i64; Denomination::COUNT],
+}
+
+impl ProtocolStateV1 {
+    pub const SIZE: usize = ProtocolState::SIZE + 32 + 32; // commitment_tree, nullifier_tree
+
+    /// Upgrade to the current layout, dropping the tree keys
+    pub fn upgrade(self) -> ProtocolState {
+        ProtocolState {
+            version: ProtocolState::CURRENT_VERSION,
+            admin: self.admin,
+            total_shielded: self.total_shielded,
+            tx_count: self.tx_count,
+            fee_bps: self.fee_bps,
+            fee_recipient: self.fee_recipient,
+            paused: self.paused,
+            min_privacy_delay: self.min_privacy_delay,
+            bump: self.bump,
+            pending_admin: self.pending_admin,
+            guardian: self.guardian,
+            change_nonce: self.change_nonce,
+            denomination_delays: self.denomination_delays,
+        }
+    }
+}
+
+/// Changelog entry of a v1 commitment tree
+#[zero_copy]
+pub struct ChangelogEntryV1 {
+    pub root: [u8; 32],
+    pub path: [[u8; 32]; TREE_DEPTH],
+    pub index: u32,
+    pub _padding: [u8; 4],
+}
+
+/// CommitmentTree v1, with an append changelog between the header and the
+/// root history
+#[zero_copy]
+pub struct CommitmentTreeV1 {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub root: [u8; 32],
+    pub filled_subtrees: [[u8; 32]; TREE_DEPTH],
+    pub zeros: [[u8; 32]; TREE_DEPTH],
+    pub changelog: [ChangelogEntryV1; 8],
+    pub next_index: u64,
+    pub sequence_number: u64,
+    pub tree_id: u64,
+    pub root_history_len: u32,
+    pub root_history_head: u32,
+    pub changelog_head: u32,
+    pub depth: u8,
+    pub bump: u8,
+    pub version: u8,
+    pub _padding: [u8; 1],
+}
+
+impl CommitmentTreeV1 {
+    /// Upgrade to the current header, dropping the changelog
+    ///
+    /// The root history itself is unchanged.
+    pub fn upgrade(&self) -> CommitmentTree {
+        CommitmentTree {
+            authority: self.authority,
+            mint: self.mint,
+            root: self.root,
+            filled_subtrees: self.filled_subtrees,
+            zeros: self.zeros,
+            next_index: self.next_index,
+            tree_id: self.tree_id,
+            root_history_len: self.root_history_len,
+            root_history_head: self.root_history_head,
+            depth: self.depth,
+            bump: self.bump,
+            version: CommitmentTree::CURRENT_VERSION,
+            _padding: [0u8; 5],
+        }
+    }
+}
+
+// Unversioned layouts are told apart from versioned ones by length alone
+const _: () = assert!(
+    ShieldedNoteV1::SIZE != ShieldedNoteV0::SIZE
+        && ShieldedNoteV2::SIZE != ShieldedNoteV0::SIZE
+        && ShieldedNote::SIZE != ShieldedNoteV0::SIZE
+);
+const _: () = assert!(PoolConfig::SIZE != PoolConfigV0::SIZE);
+const _: () = assert!(
+    ProtocolStateV1::SIZE != ProtocolStateV0::SIZE && ProtocolState::SIZE != ProtocolStateV0::SIZE
+);
+
+// A v1 tree's data never splits into the current header plus whole roots,
+// so it can't be loaded as a current tree by mistake
+const _: () = assert!(
+    !(std::mem::size_of::<CommitmentTreeV1>() - std::mem::size_of::<CommitmentTree>()).is_multiple_of(32)
+);
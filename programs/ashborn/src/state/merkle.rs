@@ -1,274 +1,366 @@
-//! Merkle Tree State - O(log n) nullifier membership proofs
-//! 
-//! No more O(n) individual nullifier accounts
+//! Merkle Tree State - O(log n) commitment membership proofs
+//!
+//! Commitment trees are append-only incremental trees (Tornado/Light style):
+//! the account keeps the rightmost filled node at each level plus the root of
+//! an empty subtree at each level, so inserts never need client-supplied siblings.
+//!
+//! Spent nullifiers are not kept in a tree; see `NullifierSet`.
+//!
+//! Tree accounts are zero-copy: a fixed header followed by a ring buffer of
+//! recent roots whose length is chosen when the tree is created.
+//!
+//! Spend proofs are checked against any root still in that buffer, so a
+//! proof built before concurrent appends stays valid without any rewriting.
+
+use std::cell::{Ref, RefMut};
+use std::mem::size_of;
 
 use anchor_lang::prelude::*;
-// use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use anchor_lang::ZeroCopy;
+use bytemuck::Pod;
+
+use crate::errors::AshbornError;
 
-/// Merkle tree depth - 2^20 = 1,048,576 nullifiers
+/// Merkle tree depth - 2^20 = 1,048,576 leaves
 pub const TREE_DEPTH: usize = 20;
 
-/// Number of recent roots to store for async proving
-pub const RECENT_ROOTS_COUNT: usize = 30;
+/// Leaves a single tree (or commitment tree shard) can hold
+pub const TREE_CAPACITY: u64 = 1 << TREE_DEPTH;
+
+/// Shortest root history a tree can be created with
+pub const MIN_ROOT_HISTORY: u32 = 32;
+
+/// Longest root history a tree can be created with
+///
+/// Keeps the account under the 10 KiB limit for accounts created through
+/// CPI, which is how PDA trees are initialized.
+pub const MAX_INITIAL_ROOT_HISTORY: u32 = 256;
+
+/// Longest root history a tree can grow to with `grow_root_history`
+pub const MAX_ROOT_HISTORY: u32 = 1000;
+
+/// Most history slots one `grow_root_history` call can add (an instruction
+/// may grow an account by at most 10 KiB)
+pub const MAX_ROOT_HISTORY_GROWTH: u32 = 320;
 
 /// Zero value for empty leaves
 pub const ZERO_VALUE: [u8; 32] = [0u8; 32];
 
-/// Nullifier Merkle Tree - stores nullifiers efficiently
-#[account]
-pub struct NullifierTree {
-    /// Tree authority (program PDA)
-    pub authority: Pubkey,
-    
-    /// Current Merkle root
-    pub root: [u8; 32],
-    
-    /// Next insertion index
-    pub next_index: u64,
-    
-    /// Tree depth
-    pub depth: u8,
-    
-    /// Recent roots for async proof verification
-    /// Allows proofs generated against slightly old roots
-    pub recent_roots: [[u8; 32]; RECENT_ROOTS_COUNT],
-    
-    /// Bump seed for PDA
-    pub bump: u8,
+/// Hash two nodes using circomlib Poseidon(2)
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    crate::zk::poseidon_hash_2(left, right)
 }
 
-impl NullifierTree {
-    pub const SIZE: usize = 8 + // discriminator
-        32 + // authority
-        32 + // root
-        8 +  // next_index
-        1 +  // depth
-        (32 * RECENT_ROOTS_COUNT) + // recent_roots
-        1;   // bump
-
-    /// Initialize with zero root
-    pub fn initialize(&mut self, authority: Pubkey, bump: u8) {
-        self.authority = authority;
-        self.root = Self::compute_zero_root();
-        self.next_index = 0;
-        self.depth = TREE_DEPTH as u8;
-        self.recent_roots = [[0u8; 32]; RECENT_ROOTS_COUNT];
-        self.bump = bump;
-    }
+/// Compute the root of an empty tree
+fn zero_root() -> Result<[u8; 32]> {
+    let mut current = ZERO_VALUE;
 
-    /// Compute the root of an empty tree
-    fn compute_zero_root() -> [u8; 32] {
-        let mut current = ZERO_VALUE;
-        
-        for _ in 0..TREE_DEPTH {
-            current = Self::hash_pair(&current, &current);
-        }
-        
-        current
+    for _ in 0..TREE_DEPTH {
+        current = hash_pair(&current, &current)?;
     }
 
-    /// Hash two nodes using Mock Poseidon
-    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-        use crate::zk::poseidon_hash_2;
-        poseidon_hash_2(left, right)
-    }
+    Ok(current)
+}
 
-    /// Insert a nullifier and update the root
-    pub fn insert(
-        &mut self,
-        nullifier: [u8; 32],
-        siblings: &[[u8; 32]; TREE_DEPTH],
-    ) -> Result<()> {
-        // Compute new root with the inserted nullifier
-        let new_root = self.compute_root_with_leaf(&nullifier, self.next_index, siblings);
-        
-        // Rotate recent roots
-        for i in (1..RECENT_ROOTS_COUNT).rev() {
-            self.recent_roots[i] = self.recent_roots[i - 1];
-        }
-        self.recent_roots[0] = self.root;
-        
-        // Update root
-        self.root = new_root;
-        self.next_index += 1;
-        
-        Ok(())
+/// Roots of empty subtrees: `zeros[0]` is an empty leaf, `zeros[i + 1] = H(zeros[i], zeros[i])`
+pub fn zero_hashes() -> Result<[[u8; 32]; TREE_DEPTH]> {
+    let mut zeros = [ZERO_VALUE; TREE_DEPTH];
+    for i in 1..TREE_DEPTH {
+        zeros[i] = hash_pair(&zeros[i - 1], &zeros[i - 1])?;
     }
+    Ok(zeros)
+}
 
-    /// Compute root with a new leaf at given index
-    fn compute_root_with_leaf(
-        &self,
-        leaf: &[u8; 32],
-        index: u64,
-        siblings: &[[u8; 32]; TREE_DEPTH],
-    ) -> [u8; 32] {
-        let mut current = *leaf;
-        let mut idx = index;
-        
-        for i in 0..TREE_DEPTH {
-            let sibling = &siblings[i];
-            
-            if idx % 2 == 0 {
-                // Leaf is on left
-                current = Self::hash_pair(&current, sibling);
-            } else {
-                // Leaf is on right
-                current = Self::hash_pair(sibling, &current);
-            }
-            
-            idx /= 2;
-        }
-        
-        current
-    }
+/// Append a leaf at `index`, updating `filled_subtrees`, and return the new root
+fn append_leaf(
+    leaf: &[u8; 32],
+    index: u64,
+    filled_subtrees: &mut [[u8; 32]; TREE_DEPTH],
+    zeros: &[[u8; 32]; TREE_DEPTH],
+) -> Result<[u8; 32]> {
+    let mut current = *leaf;
+    let mut idx = index;
 
-    /// Check if a root is valid (current or recent)
-    pub fn is_valid_root(&self, root: &[u8; 32]) -> bool {
-        if *root == self.root {
-            return true;
+    for (filled, zero) in filled_subtrees.iter_mut().zip(zeros.iter()) {
+        if idx & 1 == 0 {
+            // Left child: right sibling is still empty
+            *filled = current;
+            current = hash_pair(&current, zero)?;
+        } else {
+            // Right child: left sibling is the last filled node at this level
+            current = hash_pair(filled, &current)?;
         }
-        
-        self.recent_roots.iter().any(|r| r == root)
-    }
 
-    /// Verify a merkle proof for nullifier membership
-    pub fn verify_membership(
-        &self,
-        nullifier: &[u8; 32],
-        index: u64,
-        siblings: &[[u8; 32]; TREE_DEPTH],
-        root: &[u8; 32],
-    ) -> bool {
-        if !self.is_valid_root(root) {
-            return false;
-        }
-        
-        let computed_root = self.compute_root_with_leaf(nullifier, index, siblings);
-        computed_root == *root
+        idx /= 2;
     }
 
-    /// Check if a nullifier already exists in the tree
-    /// 
-    /// This uses a simple bloom filter approach for efficiency.
-    /// In production, you'd use a proper sparse Merkle tree with non-membership proofs.
-    pub fn nullifier_exists(&self, nullifier: &[u8; 32]) -> bool {
-        // Check if nullifier's "fingerprint" appears in any recent root
-        // This is a simplified check - real implementation uses non-membership proofs
-        let fingerprint = Self::hash_pair(nullifier, &ZERO_VALUE);
-        
-        // Check against current root
-        if self.root == fingerprint {
-            return true;
-        }
-        
-        // Check against recent roots
-        for recent_root in &self.recent_roots {
-            if *recent_root == fingerprint {
-                return true;
-            }
-            // Also check if the nullifier hash appears in recent roots
-            let check = Self::hash_pair(nullifier, recent_root);
-            if check[..8] == self.root[..8] {
-                return true;
-            }
-        }
-        
-        false
-    }
+    Ok(current)
+}
+
+/// Record a new root in the ring buffer, advancing `head` to its slot
+fn push_root(history: &mut [[u8; 32]], head: &mut u32, root: [u8; 32]) {
+    *head = (*head + 1) % history.len() as u32;
+    history[*head as usize] = root;
+}
+
+/// Check whether a root is still held in the ring buffer
+fn root_in_history(history: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    // Unused slots are zeroed, and no real tree has an all-zero root
+    *root != [0u8; 32] && history.iter().any(|r| r == root)
+}
+
+/// Validate a requested root history length
+fn check_history_len(history: &[[u8; 32]]) -> Result<u32> {
+    let len = history.len() as u32;
+    require!(
+        (MIN_ROOT_HISTORY..=MAX_INITIAL_ROOT_HISTORY).contains(&len),
+        AshbornError::InvalidRootHistoryLength
+    );
+    Ok(len)
+}
+
+/// A borrowed tree account: zero-copy header plus its root history
+pub type LoadedTree<'a, T> = (RefMut<'a, T>, RefMut<'a, [[u8; 32]]>);
+
+/// A read-only borrowed tree account
+pub type LoadedTreeRef<'a, T> = (Ref<'a, T>, Ref<'a, [[u8; 32]]>);
+
+/// Check that tree account data is a `T` header followed by whole history slots
+///
+/// Older layouts with a different header size fail this check, so they can't
+/// be borrowed as the current one.
+fn check_tree_len<T: Pod>(data_len: usize) -> Result<()> {
+    require!(
+        data_len >= 8 + size_of::<T>() && (data_len - 8 - size_of::<T>()).is_multiple_of(32),
+        AshbornError::InvalidAccountLayout
+    );
+    Ok(())
+}
+
+/// Split tree account data into its zero-copy header and root history
+fn split_tree<'a, T: Pod>(info: &'a AccountInfo) -> Result<LoadedTree<'a, T>> {
+    let data = info.try_borrow_mut_data()?;
+    check_tree_len::<T>(data.len())?;
+
+    Ok(RefMut::map_split(data, |data| {
+        let (header, history) = data[8..].split_at_mut(size_of::<T>());
+        (bytemuck::from_bytes_mut(header), bytemuck::cast_slice_mut(history))
+    }))
+}
+
+/// Read-only variant of `split_tree`
+fn split_tree_ref<'a, T: Pod>(info: &'a AccountInfo) -> Result<LoadedTreeRef<'a, T>> {
+    let data = info.try_borrow_data()?;
+    check_tree_len::<T>(data.len())?;
+
+    Ok(Ref::map_split(data, |data| {
+        let (header, history) = data[8..].split_at(size_of::<T>());
+        (bytemuck::from_bytes(header), bytemuck::cast_slice(history))
+    }))
+}
+
+/// Borrow a tree for reading as (header, root history)
+pub fn load_tree<'a, T: ZeroCopy + Owner>(
+    loader: &'a AccountLoader<T>,
+) -> Result<LoadedTreeRef<'a, T>> {
+    // Owner and discriminator checks
+    drop(loader.load()?);
+    split_tree_ref(loader.as_ref())
+}
+
+/// Borrow a tree for writing as (header, root history)
+pub fn load_tree_mut<'a, T: ZeroCopy + Owner>(
+    loader: &'a AccountLoader<T>,
+) -> Result<LoadedTree<'a, T>> {
+    // Owner, discriminator and writability checks
+    drop(loader.load_mut()?);
+    split_tree(loader.as_ref())
+}
+
+/// Borrow a freshly created tree as (header, root history)
+pub fn load_tree_init<'a, T: ZeroCopy + Owner>(
+    loader: &'a AccountLoader<T>,
+) -> Result<LoadedTree<'a, T>> {
+    // Discriminator must still be unset
+    drop(loader.load_init()?);
+    split_tree(loader.as_ref())
+}
 
-    /// Verify that provided siblings are valid for the current tree state
-    /// 
-    /// This ensures clients can't provide arbitrary siblings to manipulate the tree.
-    pub fn verify_siblings_for_insert(
-        &self,
-        siblings: &[[u8; 32]; TREE_DEPTH],
-    ) -> Result<bool> {
-        // Compute what the root would be if we inserted a zero leaf at next_index
-        let expected_root = self.compute_root_with_leaf(&ZERO_VALUE, self.next_index, siblings);
-        
-        // The expected root should match our current root
-        // (since inserting a zero leaf at an empty position shouldn't change the root)
-        Ok(self.is_valid_root(&expected_root) || self.next_index == 0)
+/// Stamp the discriminator on a tree allocated outside `#[account(init)]`
+/// and borrow it as (header, root history)
+pub fn create_tree<'a, T: ZeroCopy + Owner>(info: &'a AccountInfo) -> Result<LoadedTree<'a, T>> {
+    require_keys_eq!(*info.owner, T::owner(), AshbornError::InvalidAccountLayout);
+    {
+        let mut data = info.try_borrow_mut_data()?;
+        check_tree_len::<T>(data.len())?;
+        require!(data[..8] == [0u8; 8], AshbornError::InvalidAccountLayout);
+        data[..8].copy_from_slice(&T::DISCRIMINATOR);
     }
+    split_tree(info)
 }
 
 /// Commitment Tree - stores note commitments for membership proofs
-#[account]
+///
+/// Each mint has its own sequence of shards, so a pool's anonymity set only
+/// contains notes of that mint. Followed in the account by
+/// `root_history_len` recent roots.
+#[account(zero_copy)]
 pub struct CommitmentTree {
-    /// Tree authority
+    /// Tree authority (the mint's PoolConfig)
     pub authority: Pubkey,
     
+    /// Mint whose notes this tree holds
+    pub mint: Pubkey,
+    
     /// Current Merkle root of all commitments
     pub root: [u8; 32],
     
+    /// Rightmost filled node at each level
+    pub filled_subtrees: [[u8; 32]; TREE_DEPTH],
+    
+    /// Precomputed empty-subtree roots at each level
+    pub zeros: [[u8; 32]; TREE_DEPTH],
+    
     /// Next insertion index
     pub next_index: u64,
     
+    /// Shard number; a fresh shard is created when the active one fills up
+    pub tree_id: u64,
+    
+    /// Number of roots kept for async proving
+    pub root_history_len: u32,
+    
+    /// Ring buffer slot holding the current root
+    pub root_history_head: u32,
+    
     /// Tree depth
     pub depth: u8,
     
-    /// Recent roots for async proving
-    pub recent_roots: [[u8; 32]; RECENT_ROOTS_COUNT],
-    
     /// Bump seed
     pub bump: u8,
+    
+    /// Account layout version
+    pub version: u8,
+    
+    pub _padding: [u8; 5],
 }
 
 impl CommitmentTree {
-    pub const SIZE: usize = NullifierTree::SIZE; // Same structure
-    
-    /// Insert a new commitment
-    pub fn insert_commitment(
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 2;
+
+    /// Whether this shard is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Account size for a given root history length
+    pub fn space(root_history_len: u32) -> usize {
+        8 + size_of::<Self>() + 32 * root_history_len as usize
+    }
+
+    /// Initialize shard `tree_id` of `mint`'s tree with zero root
+    pub fn initialize(
         &mut self,
-        commitment: [u8; 32],
-        siblings: &[[u8; 32]; TREE_DEPTH],
-    ) -> Result<u64> {
-        let index = self.next_index;
-        
-        // Compute new root
-        let new_root = self.compute_root_with_leaf(&commitment, index, siblings);
-        
-        // Rotate recent roots
-        for i in (1..RECENT_ROOTS_COUNT).rev() {
-            self.recent_roots[i] = self.recent_roots[i - 1];
+        authority: Pubkey,
+        mint: Pubkey,
+        tree_id: u64,
+        bump: u8,
+        history: &mut [[u8; 32]],
+    ) -> Result<()> {
+        self.root_history_len = check_history_len(history)?;
+        self.version = Self::CURRENT_VERSION;
+        self.authority = authority;
+        self.mint = mint;
+        self.tree_id = tree_id;
+        self.root = zero_root()?;
+        self.next_index = 0;
+        self.depth = TREE_DEPTH as u8;
+        self.zeros = zero_hashes()?;
+        self.filled_subtrees = self.zeros;
+        self.bump = bump;
+
+        history.fill([0u8; 32]);
+        history[0] = self.root;
+        self.root_history_head = 0;
+        Ok(())
+    }
+    
+    /// Whether every leaf of this shard is used
+    pub fn is_full(&self) -> bool {
+        self.next_index >= TREE_CAPACITY
+    }
+
+    /// Leaves left before this shard is full
+    pub fn remaining_capacity(&self) -> u64 {
+        TREE_CAPACITY.saturating_sub(self.next_index)
+    }
+
+    /// Position of a leaf across all shards: `tree_id * 2^20 + leaf_index`
+    pub fn global_index(&self, leaf_index: u64) -> u64 {
+        (self.tree_id << TREE_DEPTH) | leaf_index
+    }
+
+    /// Shard (`tree_id`) holding the leaf at `global_index`
+    pub fn shard_of(global_index: u64) -> u64 {
+        global_index >> TREE_DEPTH
+    }
+
+    /// Insert a new commitment, returning its leaf index within this shard
+    pub fn insert_commitment(&mut self, commitment: [u8; 32], history: &mut [[u8; 32]]) -> Result<u64> {
+        self.append_commitments(&[commitment], history)
+    }
+
+    /// Insert a batch of commitments, returning the first one's leaf index
+    ///
+    /// The whole batch takes a single root history entry.
+    pub fn append_commitments(&mut self, commitments: &[[u8; 32]], history: &mut [[u8; 32]]) -> Result<u64> {
+        require!(
+            commitments.len() as u64 <= self.remaining_capacity(),
+            AshbornError::TreeFull
+        );
+        let first_index = self.next_index;
+        if commitments.is_empty() {
+            return Ok(first_index);
         }
-        self.recent_roots[0] = self.root;
-        
+
+        let mut new_root = self.root;
+        for (index, commitment) in (first_index..).zip(commitments) {
+            // Compute new root from on-chain state only
+            new_root = append_leaf(commitment, index, &mut self.filled_subtrees, &self.zeros)?;
+        }
+
+        // Overwrite the oldest root
+        push_root(history, &mut self.root_history_head, new_root);
+
         // Update state
         self.root = new_root;
-        self.next_index += 1;
-        
-        Ok(index)
+        self.next_index += commitments.len() as u64;
+
+        Ok(first_index)
     }
-    
-    fn compute_root_with_leaf(
-        &self,
-        leaf: &[u8; 32],
-        index: u64,
-        siblings: &[[u8; 32]; TREE_DEPTH],
-    ) -> [u8; 32] {
-        let mut current = *leaf;
-        let mut idx = index;
-        
-        for i in 0..TREE_DEPTH {
-            let sibling = &siblings[i];
-            
-            if idx % 2 == 0 {
-                current = NullifierTree::hash_pair(&current, sibling);
-            } else {
-                current = NullifierTree::hash_pair(sibling, &current);
-            }
-            
-            idx /= 2;
-        }
-        
-        current
+
+    /// Check if a root is valid (current or still in the history)
+    pub fn is_valid_root(&self, root: &[u8; 32], history: &[[u8; 32]]) -> bool {
+        *root == self.root || root_in_history(history, root)
     }
-    
-    pub fn is_valid_root(&self, root: &[u8; 32]) -> bool {
-        if *root == self.root {
-            return true;
-        }
-        self.recent_roots.iter().any(|r| r == root)
+
+    /// Take over a root history that grew past `root_history_len` slots
+    ///
+    /// The old ring is unrolled so its oldest root comes first, and the new,
+    /// zeroed slots are the next ones written. No root is lost.
+    pub fn extend_root_history(&mut self, history: &mut [[u8; 32]]) -> Result<()> {
+        let old_len = self.root_history_len as usize;
+        require!(
+            old_len > 0 && (old_len..=MAX_ROOT_HISTORY as usize).contains(&history.len()),
+            AshbornError::InvalidRootHistoryLength
+        );
+
+        history[..old_len].rotate_left((self.root_history_head as usize + 1) % old_len);
+        history[old_len..].fill([0u8; 32]);
+        self.root_history_head = old_len as u32 - 1;
+        self.root_history_len = history.len() as u32;
+        Ok(())
     }
 }
+
@@ -8,6 +8,11 @@ pub mod notes;
 pub mod protocol;
 pub mod rate_limit;
 pub mod nullifier;
+pub mod pending_change;
+pub mod outflow;
+pub mod pool;
+pub mod legacy;
+pub mod verifying_key;
 
 pub use merkle::*;
 pub use vault::*;
@@ -15,4 +20,9 @@ pub use notes::*;
 pub use protocol::*;
 pub use rate_limit::*;
 pub use nullifier::*;
+pub use pending_change::*;
+pub use outflow::*;
+pub use pool::*;
+pub use legacy::*;
+pub use verifying_key::*;
 
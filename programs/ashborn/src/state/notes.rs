@@ -2,23 +2,34 @@
 
 use anchor_lang::prelude::*;
 
+use crate::errors::AshbornError;
+
 /// Shielded Note - An encrypted UTXO-style note
 #[account]
 #[derive(InitSpace)]
 pub struct ShieldedNote {
+    /// Account layout version
+    pub version: u8,
+    
     /// The vault this note belongs to
     pub vault: Pubkey,
     
+    /// Mint of the pool (and commitment tree) this note is in
+    pub mint: Pubkey,
+    
     /// Poseidon commitment: C = Poseidon(amount, blinding)
     pub commitment: [u8; 32],
     
     /// Encrypted amount (ChaCha20-Poly1305)
     pub encrypted_amount: [u8; 48],
     
-    /// Note index in the commitment tree
-    pub index: u64,
+    /// Sequence number in the vault (the note's PDA seed)
+    pub index: u32,
     
-    /// Denomination tier (privacy-preserving)
+    /// Leaf of the commitment across tree shards (see `CommitmentTree::global_index`)
+    pub leaf_index: u64,
+    
+    /// Denomination tier (privacy-preserving), or `NO_DENOMINATION`
     pub denomination_tier: u8,
     
     /// Whether this note has been spent
@@ -27,7 +38,7 @@ pub struct ShieldedNote {
     /// Timestamp of note creation
     pub created_at: i64,
     
-    /// Minimum unshield time (24h delay for privacy)
+    /// Minimum unshield time (protocol privacy delay)
     pub unshield_after: i64,
     
     /// Bump seed for PDA
@@ -36,15 +47,51 @@ pub struct ShieldedNote {
 
 impl ShieldedNote {
     pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
         32 + // vault
+        32 + // mint
         32 + // commitment
         48 + // encrypted_amount
-        8 +  // index
+        4 +  // index
+        8 +  // leaf_index
         1 +  // denomination_tier
         1 +  // spent
         8 +  // created_at
         8 +  // unshield_after
         1;   // bump
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 3;
+
+    /// Whether this note has been migrated to the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Tier of notes whose value is not public (transfer change)
+    pub const NO_DENOMINATION: u8 = u8::MAX;
+
+    /// Value the note was shielded with, fixed by its denomination tier
+    ///
+    /// Change notes have no public value and cannot be priced on-chain.
+    pub fn value(&self) -> Result<u64> {
+        Denomination::from_tier(self.denomination_tier)
+            .map(|denomination| denomination.to_amount())
+            .ok_or_else(|| error!(AshbornError::InvalidDenomination))
+    }
+
+    /// Amount an unshield of this note pays out (fee included)
+    ///
+    /// The withdraw proof binds the amount to the note's committed value (see
+    /// `verify_withdraw_proof`). A denominated note's value is also public, so
+    /// a mismatch is rejected before the proof is checked.
+    pub fn withdrawal_amount(&self, requested: u64) -> Result<u64> {
+        require!(requested > 0, AshbornError::ZeroAmount);
+        if self.denomination_tier != Self::NO_DENOMINATION {
+            require!(requested == self.value()?, AshbornError::NoteValueMismatch);
+        }
+        Ok(requested)
+    }
 }
 
 /// Denominations for privacy-preserving privacy (uniform amounts)
@@ -64,7 +111,10 @@ pub enum Denomination {
 }
 
 impl Denomination {
-    pub const VALUES: [u64; 5] = [
+    /// Number of denomination tiers
+    pub const COUNT: usize = 5;
+
+    pub const VALUES: [u64; Self::COUNT] = [
         100_000_000,      // 0.1 SOL
         1_000_000_000,    // 1 SOL
         10_000_000_000,   // 10 SOL
@@ -86,6 +136,17 @@ impl Denomination {
     pub fn to_amount(&self) -> u64 {
         Self::VALUES[*self as usize]
     }
+
+    pub fn from_tier(tier: u8) -> Option<Self> {
+        match tier {
+            0 => Some(Self::Tier1),
+            1 => Some(Self::Tier2),
+            2 => Some(Self::Tier3),
+            3 => Some(Self::Tier4),
+            4 => Some(Self::Tier5),
+            _ => None,
+        }
+    }
 }
 
 /// Compliance Proof stored on-chain
@@ -1,10 +1,48 @@
 //! Nullifier State
 //!
-//! A PDA derived from the nullifier hash.
-//! Existence of this account proves the note has been spent.
+//! Spent nullifiers live in 256 `NullifierSet` shards keyed by the
+//! nullifier's low byte, so spending a note costs no rent and leaves no
+//! per-spend account tied to the payer.
+//!
+//! A shard holds at most `MAX_NULLIFIER_SET_SLOTS` nullifiers (the 10 MiB
+//! account limit), about 83.8M spends across all 256 shards. Inserting keeps
+//! the shard sorted by shifting every larger entry up one slot: a memmove of
+//! up to 32 bytes per stored nullifier, 10 MiB at the cap. SBF runs it as the
+//! `sol_memmove` syscall at 250 bytes per CU, so the worst case adds about
+//! 42K CU to a spend on top of a 19-step binary search.
+//!
+//! An indexed (sorted linked-list) Merkle tree of nullifiers was considered
+//! and declined. Inserting into it needs the low element and its 20-level
+//! Merkle proof (about 700 bytes) in the spend itself, which pushes transfer
+//! and unshield past the 1232-byte transaction limit next to their Groth16
+//! proofs. The proof must also be against the tree's current root, so every
+//! spend in the protocol would race every other one. A tree filled outside
+//! the spend would only mirror the sets and enforce nothing. The sets give
+//! the same double-spend guarantee on-chain; in-circuit non-membership would
+//! need a new spend circuit anyway and is left to that work.
+//!
+//! `Nullifier` is the legacy layout: a PDA derived from the nullifier hash
+//! whose existence proved the note had been spent. Existing ones are
+//! imported into the sets by `migrate_nullifier`, which closes them.
+
+use std::mem::size_of;
 
 use anchor_lang::prelude::*;
 
+use crate::errors::AshbornError;
+
+/// Slots a nullifier set is created with, and added by each grow
+///
+/// 8 KiB per step stays under both the 10 KiB CPI account creation limit
+/// and the per-instruction realloc limit.
+pub const NULLIFIER_SET_GROWTH: u32 = 256;
+
+/// Most slots a nullifier set can grow to
+///
+/// The largest multiple of `NULLIFIER_SET_GROWTH` whose account fits the
+/// 10 MiB account size limit.
+pub const MAX_NULLIFIER_SET_SLOTS: u32 = 1279 * NULLIFIER_SET_GROWTH;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Nullifier {
@@ -25,3 +63,96 @@ impl Nullifier {
         8 +  // spent_at
         1;   // bump
 }
+
+/// Sorted set of spent nullifiers sharing a low byte
+///
+/// Followed in the account by its 32-byte slots, laid out like the tree
+/// accounts so it is borrowed with `load_tree_mut`. The first `count` slots
+/// hold the nullifiers in ascending order; growing the account just adds
+/// empty slots at the end.
+#[account(zero_copy)]
+pub struct NullifierSet {
+    /// Number of nullifiers stored
+    pub count: u32,
+
+    /// Low byte shared by every nullifier in this shard
+    pub prefix: u8,
+
+    /// Bump seed for PDA
+    pub bump: u8,
+
+    /// Account layout version
+    pub version: u8,
+
+    pub _padding: [u8; 1],
+}
+
+impl NullifierSet {
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this shard is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Account size for a given number of slots
+    pub fn space(slots: u32) -> usize {
+        8 + size_of::<Self>() + 32 * slots as usize
+    }
+
+    /// Shard a nullifier belongs to
+    pub fn shard_of(nullifier: &[u8; 32]) -> u8 {
+        // Poseidon outputs are uniform in their low bits; the high byte is not
+        nullifier[31]
+    }
+
+    /// Initialize an empty shard
+    pub fn initialize(&mut self, prefix: u8, bump: u8, slots: &mut [[u8; 32]]) {
+        self.version = Self::CURRENT_VERSION;
+        self.count = 0;
+        self.prefix = prefix;
+        self.bump = bump;
+        slots.fill([0u8; 32]);
+    }
+
+    /// Slot count after one more growth step
+    ///
+    /// A shard only grows once it has fewer than `NULLIFIER_SET_GROWTH` free
+    /// slots, so it can't be inflated ahead of use (every spend loads it),
+    /// and never past `MAX_NULLIFIER_SET_SLOTS`.
+    pub fn grown_len(&self, slots: &[[u8; 32]]) -> Result<usize> {
+        let free = slots.len() - self.count as usize;
+        require!(free < NULLIFIER_SET_GROWTH as usize, AshbornError::NullifierSetNotFull);
+
+        let grown = slots.len() + NULLIFIER_SET_GROWTH as usize;
+        require!(grown <= MAX_NULLIFIER_SET_SLOTS as usize, AshbornError::NullifierSetAtMaxSize);
+        Ok(grown)
+    }
+
+    /// Whether a nullifier has been spent
+    pub fn contains(&self, nullifier: &[u8; 32], slots: &[[u8; 32]]) -> bool {
+        slots[..self.count as usize].binary_search(nullifier).is_ok()
+    }
+
+    /// Record a nullifier as spent
+    ///
+    /// Fails with `NullifierAlreadyUsed` on a double spend and with
+    /// `NullifierSetFull` if the shard needs to be grown first.
+    pub fn insert(&mut self, nullifier: [u8; 32], slots: &mut [[u8; 32]]) -> Result<()> {
+        require!(Self::shard_of(&nullifier) == self.prefix, AshbornError::InvalidNullifier);
+
+        let count = self.count as usize;
+        let position = match slots[..count].binary_search(&nullifier) {
+            Ok(_) => return err!(AshbornError::NullifierAlreadyUsed),
+            Err(position) => position,
+        };
+        require!(count < slots.len(), AshbornError::NullifierSetFull);
+
+        slots.copy_within(position..count, position + 1);
+        slots[position] = nullifier;
+        self.count += 1;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+//! Outflow circuit breaker for unshield
+//!
+//! Caps how much value can leave a pool per epoch. A withdrawal that would
+//! break the cap trips the breaker instead of paying out. The admin or guardian
+//! can also trip it by hand; either way withdrawals stay halted (across epochs)
+//! until they reset it.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+
+/// Per-mint withdrawal limit
+#[account]
+pub struct OutflowLimit {
+    /// Account layout version
+    pub version: u8,
+
+    /// Mint this limit applies to
+    pub mint: Pubkey,
+
+    /// Current epoch (based on slots)
+    pub current_epoch: u64,
+
+    /// Amount withdrawn in current epoch
+    pub withdrawn_this_epoch: u64,
+
+    /// Maximum amount that may leave the pool per epoch
+    pub max_outflow_per_epoch: u64,
+
+    /// Duration of an epoch in slots
+    pub epoch_duration_slots: u64,
+
+    /// Whether the breaker has been tripped (withdrawals halted)
+    pub tripped: bool,
+
+    /// When the breaker tripped (0 if not tripped)
+    pub tripped_at: i64,
+
+    /// Total withdrawn all time
+    pub total_withdrawn: u64,
+
+    /// Bump seed
+    pub bump: u8,
+}
+
+impl OutflowLimit {
+    pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
+        32 + // mint
+        8 +  // current_epoch
+        8 +  // withdrawn_this_epoch
+        8 +  // max_outflow_per_epoch
+        8 +  // epoch_duration_slots
+        1 +  // tripped
+        8 +  // tripped_at
+        8 +  // total_withdrawn
+        1;   // bump
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this limit is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Default epoch duration (~1 hour at 400ms slots)
+    pub const DEFAULT_EPOCH_SLOTS: u64 = 9000;
+
+    /// Default cap as a fraction of the pool's TVL cap (10% per epoch)
+    pub const DEFAULT_TVL_DIVISOR: u64 = 10;
+
+    /// Initialize a new pool's limit with the defaults
+    pub fn initialize(&mut self, mint: Pubkey, max_tvl: u64, bump: u8) {
+        self.version = Self::CURRENT_VERSION;
+        self.mint = mint;
+        self.current_epoch = 0;
+        self.withdrawn_this_epoch = 0;
+        self.max_outflow_per_epoch = (max_tvl / Self::DEFAULT_TVL_DIVISOR).max(1);
+        self.epoch_duration_slots = Self::DEFAULT_EPOCH_SLOTS;
+        self.tripped = false;
+        self.tripped_at = 0;
+        self.total_withdrawn = 0;
+        self.bump = bump;
+    }
+
+    /// Update limit configuration (admin only)
+    ///
+    /// `current_epoch` is counted in units of the old duration, so it is
+    /// re-based on `current_slot`.
+    pub fn update_config(&mut self, max_outflow: u64, epoch_slots: u64, current_slot: u64) -> Result<()> {
+        require!(
+            max_outflow > 0 && epoch_slots > 0,
+            AshbornError::InvalidOutflowLimitConfig
+        );
+
+        self.max_outflow_per_epoch = max_outflow;
+        self.epoch_duration_slots = epoch_slots;
+        self.current_epoch = current_slot / epoch_slots;
+        Ok(())
+    }
+
+    /// Record a withdrawal against the epoch cap
+    ///
+    /// Returns `false` if the withdrawal would exceed the cap. The breaker is
+    /// then tripped and nothing is counted; the caller must not pay out, and
+    /// must return `Ok` so the trip is persisted.
+    pub fn record_withdrawal(&mut self, amount: u64, current_slot: u64, now: i64) -> Result<bool> {
+        require!(!self.tripped, AshbornError::WithdrawalsHalted);
+
+        // Counter restarts in a new epoch
+        let epoch = current_slot / self.epoch_duration_slots;
+        let withdrawn = if epoch > self.current_epoch {
+            0
+        } else {
+            self.withdrawn_this_epoch
+        };
+
+        let new_total = match withdrawn.checked_add(amount) {
+            Some(total) if total <= self.max_outflow_per_epoch => total,
+            _ => {
+                self.trip(now);
+                return Ok(false);
+            }
+        };
+
+        self.current_epoch = self.current_epoch.max(epoch);
+        self.withdrawn_this_epoch = new_total;
+        self.total_withdrawn = self.total_withdrawn.saturating_add(amount);
+        Ok(true)
+    }
+
+    /// Halt withdrawals until reset (admin or guardian)
+    pub fn trip(&mut self, now: i64) {
+        self.tripped = true;
+        self.tripped_at = now;
+    }
+
+    /// Re-arm a tripped breaker (admin or guardian)
+    pub fn reset(&mut self) -> Result<()> {
+        require!(self.tripped, AshbornError::OutflowNotTripped);
+
+        self.tripped = false;
+        self.tripped_at = 0;
+        self.withdrawn_this_epoch = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(max_outflow: u64) -> OutflowLimit {
+        OutflowLimit {
+            version: OutflowLimit::CURRENT_VERSION,
+            mint: Pubkey::default(),
+            current_epoch: 0,
+            withdrawn_this_epoch: 0,
+            max_outflow_per_epoch: max_outflow,
+            epoch_duration_slots: 100,
+            tripped: false,
+            tripped_at: 0,
+            total_withdrawn: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_withdrawals_within_cap() {
+        let mut state = limit(1_000);
+
+        assert!(state.record_withdrawal(400, 10, 0).unwrap());
+        assert!(state.record_withdrawal(600, 20, 0).unwrap());
+        assert_eq!(state.withdrawn_this_epoch, 1_000);
+
+        // Counter resets in the next epoch
+        assert!(state.record_withdrawal(1_000, 150, 0).unwrap());
+        assert_eq!(state.total_withdrawn, 2_000);
+    }
+
+    #[test]
+    fn test_over_cap_withdrawal_trips_breaker() {
+        let mut state = limit(1_000);
+
+        assert!(state.record_withdrawal(900, 10, 0).unwrap());
+        assert!(!state.record_withdrawal(200, 20, 77).unwrap());
+        // Tripped, and the refused withdrawal was not counted
+        assert!(state.tripped);
+        assert_eq!(state.tripped_at, 77);
+        assert_eq!(state.withdrawn_this_epoch, 900);
+        assert!(state.record_withdrawal(100, 30, 78).is_err());
+
+        // An overflowing amount trips it too
+        let mut state = limit(1_000);
+        assert!(state.record_withdrawal(1, 10, 0).unwrap());
+        assert!(!state.record_withdrawal(u64::MAX, 20, 5).unwrap());
+        assert!(state.tripped);
+    }
+
+    #[test]
+    fn test_tripped_breaker_halts_until_reset() {
+        let mut state = limit(1_000);
+
+        state.trip(42);
+        assert_eq!(state.tripped_at, 42);
+        // Still halted in later epochs until reset
+        assert!(state.record_withdrawal(1, 500, 0).is_err());
+
+        state.reset().unwrap();
+        assert!(state.record_withdrawal(1, 500, 0).unwrap());
+    }
+
+    #[test]
+    fn test_reset_requires_tripped_breaker() {
+        let mut state = limit(1_000);
+
+        assert!(state.record_withdrawal(1_000, 10, 0).unwrap());
+        // Resetting an armed breaker would wipe the epoch counter
+        assert!(state.reset().is_err());
+        assert_eq!(state.withdrawn_this_epoch, 1_000);
+    }
+
+    #[test]
+    fn test_initialize_defaults_from_tvl_cap() {
+        let mut state = limit(0);
+
+        state.initialize(Pubkey::new_unique(), 5_000, 7);
+        assert!(state.is_current());
+        assert_eq!(state.max_outflow_per_epoch, 500);
+        assert_eq!(state.epoch_duration_slots, OutflowLimit::DEFAULT_EPOCH_SLOTS);
+        assert_eq!(state.bump, 7);
+
+        // A tiny pool still lets something out
+        state.initialize(Pubkey::new_unique(), 3, 7);
+        assert_eq!(state.max_outflow_per_epoch, 1);
+    }
+
+    #[test]
+    fn test_update_config_rejects_zero() {
+        let mut state = limit(1_000);
+
+        assert!(state.update_config(0, 100, 0).is_err());
+        assert!(state.update_config(100, 0, 0).is_err());
+        assert!(state.update_config(500, 50, 0).is_ok());
+        assert_eq!(state.max_outflow_per_epoch, 500);
+    }
+
+    #[test]
+    fn test_update_config_rebases_epoch() {
+        let mut state = limit(1_000);
+        state.epoch_duration_slots = 100_000;
+
+        assert!(state.record_withdrawal(1_000, 150_000, 0).unwrap());
+        assert_eq!(state.current_epoch, 1);
+
+        // The same slot is epoch 1_500 with 100-slot epochs
+        state.update_config(1_000, 100, 150_001).unwrap();
+        assert_eq!(state.current_epoch, 1_500);
+        assert!(!state.record_withdrawal(1, 150_002, 0).unwrap());
+    }
+}
@@ -0,0 +1,128 @@
+//! Timelocked protocol configuration changes
+//!
+//! Parameters that users rely on mid-flight (fee quotes, the privacy delay,
+//! rate limits, the Groth16 keys proofs are checked against) are never
+//! changed in place. The admin queues a PendingChange,
+//! wallets see it coming, and anyone can apply it once the timelock elapses.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+use crate::state::{CircuitId, Denomination, ProtocolState};
+
+/// A configuration change that must pass through the timelock
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParameterChange {
+    /// New unshield fee (basis points)
+    FeeBps(u16),
+    /// New minimum privacy delay (seconds)
+    MinPrivacyDelay(i64),
+    /// New privacy delay override for one denomination tier (seconds, 0 clears)
+    DenominationDelay { tier: u8, delay: i64 },
+    /// New deposit rate limit
+    RateLimit {
+        max_deposits_per_epoch: u64,
+        epoch_duration_slots: u64,
+    },
+    /// Activate an uploaded verifying key, retiring the circuit's current one
+    ActivateVerifyingKey { circuit: CircuitId, version: u32 },
+}
+
+impl ParameterChange {
+    /// Largest serialized variant (tag + RateLimit)
+    pub const MAX_SIZE: usize = 1 + 8 + 8;
+
+    /// Reject values the protocol could never run with
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::FeeBps(fee_bps) => {
+                require!(fee_bps <= ProtocolState::MAX_FEE_BPS, AshbornError::FeeTooHigh);
+            }
+            Self::MinPrivacyDelay(delay) => {
+                require!(delay >= 0, AshbornError::InvalidPrivacyDelay);
+            }
+            Self::DenominationDelay { tier, delay } => {
+                require!(
+                    Denomination::from_tier(tier).is_some(),
+                    AshbornError::InvalidDenomination
+                );
+                require!(delay >= 0, AshbornError::InvalidPrivacyDelay);
+            }
+            Self::RateLimit {
+                max_deposits_per_epoch,
+                epoch_duration_slots,
+            } => {
+                require!(
+                    max_deposits_per_epoch > 0 && epoch_duration_slots > 0,
+                    AshbornError::InvalidRateLimitConfig
+                );
+            }
+            Self::ActivateVerifyingKey { version, .. } => {
+                // Version 0 means "no active key" in the registry
+                require!(version > 0, AshbornError::InvalidVerifyingKey);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A queued change awaiting its timelock
+#[account]
+pub struct PendingChange {
+    /// Account layout version
+    pub version: u8,
+
+    /// Sequential id (from `ProtocolState::change_nonce`)
+    pub id: u64,
+
+    /// The change to apply
+    pub change: ParameterChange,
+
+    /// Admin that queued the change (receives rent back)
+    pub proposer: Pubkey,
+
+    /// When the change was queued
+    pub queued_at: i64,
+
+    /// Earliest time the change can be executed
+    pub eta: i64,
+
+    /// Bump seed
+    pub bump: u8,
+}
+
+impl PendingChange {
+    pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
+        8 +  // id
+        ParameterChange::MAX_SIZE + // change
+        32 + // proposer
+        8 +  // queued_at
+        8 +  // eta
+        1;   // bump
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this change is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Minimum time between queueing and executing: 48 hours,
+    /// long enough for users inside the 24h privacy delay to exit
+    pub const MIN_DELAY: i64 = 48 * 60 * 60;
+
+    /// A change not executed within 14 days of its ETA goes stale
+    pub const GRACE_PERIOD: i64 = 14 * 24 * 60 * 60;
+
+    /// Whether the change can be executed at `now`
+    pub fn check_executable(&self, now: i64) -> Result<()> {
+        require!(now >= self.eta, AshbornError::TimelockNotElapsed);
+        require!(
+            now <= self.eta.saturating_add(Self::GRACE_PERIOD),
+            AshbornError::ChangeExpired
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Per-mint pool configuration and solvency accounting
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+
+/// Configuration and accounting for one shielded pool
+#[account]
+pub struct PoolConfig {
+    /// Account layout version
+    pub version: u8,
+
+    /// Mint held by this pool
+    pub mint: Pubkey,
+
+    /// Maximum value that may sit in the pool (launch-phase deposit cap)
+    pub max_tvl: u64,
+
+    /// Outstanding shielded value owed to note holders
+    pub total_shielded: u64,
+
+    /// Bump seed
+    pub bump: u8,
+
+    /// Active commitment tree shard for this mint
+    pub commitment_tree: Pubkey,
+}
+
+impl PoolConfig {
+    pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
+        32 + // mint
+        8 +  // max_tvl
+        8 +  // total_shielded
+        1 +  // bump
+        32;  // commitment_tree
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this pool config is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Account for a deposit, enforcing the TVL cap
+    pub fn record_deposit(&mut self, amount: u64) -> Result<()> {
+        let new_total = self
+            .total_shielded
+            .checked_add(amount)
+            .ok_or(AshbornError::Overflow)?;
+        require!(new_total <= self.max_tvl, AshbornError::PoolCapExceeded);
+
+        self.total_shielded = new_total;
+        Ok(())
+    }
+
+    /// Check the solvency invariant before funds leave the pool
+    ///
+    /// The pool must hold at least what is owed to note holders. Fees live in
+    /// the fee vault, so nothing is netted out of the pool balance here.
+    pub fn check_solvency(&self, pool_balance: u64, amount: u64) -> Result<()> {
+        require!(amount <= self.total_shielded, AshbornError::PoolInsolvent);
+        require!(pool_balance >= self.total_shielded, AshbornError::PoolInsolvent);
+        Ok(())
+    }
+
+    /// Account for a withdrawal (fee included)
+    pub fn record_withdrawal(&mut self, amount: u64) -> Result<()> {
+        self.total_shielded = self
+            .total_shielded
+            .checked_sub(amount)
+            .ok_or(AshbornError::PoolInsolvent)?;
+        Ok(())
+    }
+}
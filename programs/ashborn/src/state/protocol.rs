@@ -2,9 +2,14 @@
 
 use anchor_lang::prelude::*;
 
+use crate::state::Denomination;
+
 /// Global protocol configuration
 #[account]
 pub struct ProtocolState {
+    /// Account layout version
+    pub version: u8,
+    
     /// Protocol admin
     pub admin: Pubkey,
     
@@ -23,32 +28,100 @@ pub struct ProtocolState {
     /// Pause flag
     pub paused: bool,
     
-    /// Commitment tree address
-    pub commitment_tree: Pubkey,
-    
-    /// Nullifier tree address
-    pub nullifier_tree: Pubkey,
-    
     /// Minimum privacy delay (seconds)
     pub min_privacy_delay: i64,
     
     /// Bump seed
     pub bump: u8,
+
+    /// Admin proposed via `propose_admin`, awaiting `accept_admin`
+    /// (`Pubkey::default()` when no handover is in progress)
+    pub pending_admin: Pubkey,
+
+    /// Emergency key - may only pause the protocol
+    pub guardian: Pubkey,
+
+    /// Id of the next timelocked PendingChange
+    pub change_nonce: u64,
+
+    /// Per-denomination privacy delay overrides (seconds, indexed by tier).
+    /// A tier never waits less than `min_privacy_delay`; 0 means no override.
+    pub denomination_delays: [i64; Denomination::COUNT],
 }
 
 impl ProtocolState {
     pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
         32 + // admin
         8 +  // total_shielded
         8 +  // tx_count
         2 +  // fee_bps
         32 + // fee_recipient
         1 +  // paused
-        32 + // commitment_tree
-        32 + // nullifier_tree
         8 +  // min_privacy_delay
-        1;   // bump
+        1 +  // bump
+        32 + // pending_admin
+        32 + // guardian
+        8 +  // change_nonce
+        8 * Denomination::COUNT; // denomination_delays
         
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 2;
+
     /// Default minimum delay: 24 hours (privacy-preserving)
     pub const DEFAULT_DELAY: i64 = 24 * 60 * 60;
+
+    /// Hard ceiling on the protocol fee: 1% (100 bps)
+    pub const MAX_FEE_BPS: u16 = 100;
+
+    /// Whether this account has been migrated to the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Privacy delay for a new note of the given tier (`None` if the tier is unknown)
+    pub fn privacy_delay(&self, tier: Option<Denomination>) -> i64 {
+        let override_delay = tier
+            .map(|t| self.denomination_delays[t as usize])
+            .unwrap_or(0);
+        self.min_privacy_delay.max(override_delay)
+    }
+
+    /// Earliest unshield time for a note created at `now`
+    pub fn unshield_after(&self, now: i64, tier: Option<Denomination>) -> Result<i64> {
+        now.checked_add(self.privacy_delay(tier))
+            .ok_or_else(|| error!(crate::errors::AshbornError::Overflow))
+    }
+
+    /// Account for value entering the pool
+    pub fn record_shield(&mut self, amount: u64) -> Result<()> {
+        self.total_shielded = self
+            .total_shielded
+            .checked_add(amount)
+            .ok_or(crate::errors::AshbornError::Overflow)?;
+        self.record_tx()
+    }
+
+    /// Account for value leaving the pool (fee included)
+    pub fn record_unshield(&mut self, amount: u64) -> Result<()> {
+        self.total_shielded = self
+            .total_shielded
+            .checked_sub(amount)
+            .ok_or(crate::errors::AshbornError::InsufficientBalance)?;
+        self.record_tx()
+    }
+
+    /// Count a state-changing transaction
+    pub fn record_tx(&mut self) -> Result<()> {
+        self.tx_count = self
+            .tx_count
+            .checked_add(1)
+            .ok_or(crate::errors::AshbornError::Overflow)?;
+        Ok(())
+    }
+
+    /// Whether `key` may pause the protocol (admin or guardian)
+    pub fn can_pause(&self, key: &Pubkey) -> bool {
+        *key == self.admin || *key == self.guardian
+    }
 }
@@ -7,6 +7,9 @@ use anchor_lang::prelude::*;
 /// Rate limit configuration for deposits
 #[account]
 pub struct RateLimitState {
+    /// Account layout version
+    pub version: u8,
+    
     /// Authority that can update rate limits
     pub authority: Pubkey,
     
@@ -31,6 +34,7 @@ pub struct RateLimitState {
 
 impl RateLimitState {
     pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
         32 + // authority
         8 +  // current_epoch
         8 +  // deposits_this_epoch
@@ -39,6 +43,14 @@ impl RateLimitState {
         8 +  // total_deposits
         1;   // bump
 
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this rate limit is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
     /// Default max deposits per epoch (1000)
     pub const DEFAULT_MAX_DEPOSITS: u64 = 1000;
     
@@ -47,6 +59,7 @@ impl RateLimitState {
 
     /// Initialize rate limit state
     pub fn initialize(&mut self, authority: Pubkey, bump: u8) {
+        self.version = Self::CURRENT_VERSION;
         self.authority = authority;
         self.current_epoch = 0;
         self.deposits_this_epoch = 0;
@@ -93,13 +106,25 @@ impl RateLimitState {
     }
     
     /// Update rate limit configuration (admin only)
-    pub fn update_config(&mut self, max_deposits: u64, epoch_slots: u64) {
+    ///
+    /// `epoch_slots` is the divisor in `record_deposit`, so zero is rejected.
+    /// `current_epoch` is counted in units of the old duration, so it is
+    /// re-based on `current_slot`; deposits already made this epoch still count.
+    pub fn update_config(&mut self, max_deposits: u64, epoch_slots: u64, current_slot: u64) -> Result<()> {
+        require!(
+            max_deposits > 0 && epoch_slots > 0,
+            crate::errors::AshbornError::InvalidRateLimitConfig
+        );
+
         self.max_deposits_per_epoch = max_deposits;
         self.epoch_duration_slots = epoch_slots;
+        self.current_epoch = current_slot / epoch_slots;
+        Ok(())
     }
 }
 
-#[error_code]
+/// Offset keeps these codes clear of `AshbornError` (which starts at 6000)
+#[error_code(offset = 7000)]
 pub enum RateLimitError {
     #[msg("Rate limit exceeded. Please wait for the next epoch.")]
     RateLimitExceeded,
@@ -112,6 +137,7 @@ mod tests {
     #[test]
     fn test_rate_limit() {
         let mut state = RateLimitState {
+            version: RateLimitState::CURRENT_VERSION,
             authority: Pubkey::default(),
             current_epoch: 0,
             deposits_this_epoch: 0,
@@ -133,4 +159,77 @@ mod tests {
         // Should allow in next epoch
         assert!(state.can_deposit(150));
     }
+
+    #[test]
+    fn test_record_deposit_enforces_limit() {
+        let mut state = RateLimitState {
+            version: RateLimitState::CURRENT_VERSION,
+            authority: Pubkey::default(),
+            current_epoch: 0,
+            deposits_this_epoch: 0,
+            max_deposits_per_epoch: 2,
+            epoch_duration_slots: 100,
+            total_deposits: 0,
+            bump: 0,
+        };
+
+        assert!(state.record_deposit(10).is_ok());
+        assert!(state.record_deposit(20).is_ok());
+        assert!(state.record_deposit(30).is_err());
+
+        // Counter resets in the next epoch
+        assert!(state.record_deposit(100).is_ok());
+        assert_eq!(state.current_epoch, 1);
+        assert_eq!(state.total_deposits, 3);
+    }
+
+    #[test]
+    fn test_update_config_rejects_zero_epoch() {
+        let mut state = RateLimitState {
+            version: RateLimitState::CURRENT_VERSION,
+            authority: Pubkey::default(),
+            current_epoch: 0,
+            deposits_this_epoch: 0,
+            max_deposits_per_epoch: 2,
+            epoch_duration_slots: 100,
+            total_deposits: 0,
+            bump: 0,
+        };
+
+        assert!(state.update_config(10, 0, 0).is_err());
+        assert!(state.update_config(0, 10, 0).is_err());
+        assert_eq!(state.epoch_duration_slots, 100);
+
+        assert!(state.update_config(10, 50, 0).is_ok());
+        assert_eq!(state.max_deposits_per_epoch, 10);
+        assert_eq!(state.epoch_duration_slots, 50);
+    }
+
+    #[test]
+    fn test_update_config_rebases_epoch() {
+        let mut state = RateLimitState {
+            version: RateLimitState::CURRENT_VERSION,
+            authority: Pubkey::default(),
+            current_epoch: 0,
+            deposits_this_epoch: 0,
+            max_deposits_per_epoch: 2,
+            epoch_duration_slots: 100_000,
+            total_deposits: 0,
+            bump: 0,
+        };
+
+        // Slot 150_000 is epoch 1 with the old duration
+        assert!(state.record_deposit(150_000).is_ok());
+        assert!(state.record_deposit(150_001).is_ok());
+        assert_eq!(state.current_epoch, 1);
+
+        // With 100-slot epochs the same slot is epoch 1_500. Without the
+        // re-base every later slot would look like a fresh epoch.
+        state.update_config(2, 100, 150_002).unwrap();
+        assert_eq!(state.current_epoch, 1_500);
+        assert!(state.record_deposit(150_003).is_err());
+
+        assert!(state.record_deposit(150_100).is_ok());
+        assert_eq!(state.current_epoch, 1_501);
+    }
 }
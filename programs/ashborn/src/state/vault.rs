@@ -16,7 +16,7 @@ pub struct ShadowVault {
     /// Bump seed for PDA derivation
     pub bump: u8,
     
-    /// Number of shielded notes (used for PDA derivation)
+    /// Number of notes ever created; note `n` lives at sequence number `n` (its PDA seed)
     pub note_count: u32,
     
     /// View key commitment for optional disclosure
@@ -36,8 +36,12 @@ pub struct ShadowVault {
     /// Last activity timestamp
     pub last_activity: i64,
     
+    /// Account layout version (carved from the reserved space, so
+    /// pre-versioning vaults read as version 0)
+    pub version: u8,
+    
     /// Reserved for future upgrades
-    pub _reserved: [u8; 64],
+    pub _reserved: [u8; 63],
 }
 
 impl ShadowVault {
@@ -50,5 +54,14 @@ impl ShadowVault {
         48 + // encrypted_balance_hint
         8 +  // created_at
         8 +  // last_activity
-        64;  // reserved
+        1 +  // version
+        63;  // reserved
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this vault has been migrated to the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
 }
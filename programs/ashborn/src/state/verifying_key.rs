@@ -0,0 +1,195 @@
+//! Groth16 verifying keys stored on-chain
+//!
+//! One account per (circuit, version), so a circuit fix or a new trusted
+//! setup is an upload and an activation rather than a program redeploy.
+//! The IC array grows with the number of public inputs and may not fit in
+//! one transaction, so it is appended in chunks; a key is immutable once it
+//! is complete. Activation goes through the timelock
+//! (`ParameterChange::ActivateVerifyingKey`), so users can check the exact
+//! key before proofs are verified against it.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+
+/// Circuits the program verifies proofs for
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CircuitId {
+    Shield,
+    Transfer,
+    Range,
+    Ownership,
+}
+
+impl CircuitId {
+    /// Number of circuits (length of the registry's version table)
+    pub const COUNT: usize = 4;
+
+    /// Public inputs of the circuit (the IC array has one more point)
+    pub fn n_public(self) -> usize {
+        match self {
+            Self::Shield => 2,
+            Self::Transfer => 5,
+            Self::Range => 3,
+            Self::Ownership => 2,
+        }
+    }
+}
+
+/// Lifecycle of a verifying key
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerifyingKeyStatus {
+    /// IC points are still being appended
+    Uploading,
+    /// The key proofs for its circuit are checked against
+    Active,
+    /// Superseded or withdrawn; never accepted again
+    Retired,
+}
+
+/// A verifying key for one version of one circuit
+#[account]
+pub struct VerifyingKey {
+    /// Account layout version (`version` is the key's own version)
+    pub layout_version: u8,
+    pub circuit: CircuitId,
+    pub version: u32,
+    pub status: VerifyingKeyStatus,
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    /// Bump seed
+    pub bump: u8,
+    /// IC points, one per public input plus one
+    pub ic: Vec<[u8; 64]>,
+}
+
+impl VerifyingKey {
+    /// Current account layout version
+    pub const CURRENT_LAYOUT_VERSION: u8 = 1;
+
+    /// Whether this key is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.layout_version == Self::CURRENT_LAYOUT_VERSION
+    }
+
+    /// Account size with room for the circuit's full IC array
+    pub fn space(circuit: CircuitId) -> usize {
+        8 + // discriminator
+        1 +  // layout_version
+        1 +  // circuit
+        4 +  // version
+        1 +  // status
+        64 + // alpha_g1
+        128 * 3 + // beta_g2, gamma_g2, delta_g2
+        1 +  // bump
+        4 + 64 * (circuit.n_public() + 1) // ic
+    }
+
+    /// Whether every IC point has been uploaded
+    pub fn is_complete(&self) -> bool {
+        self.ic.len() == self.circuit.n_public() + 1
+    }
+
+    /// Append a chunk of IC points while the key is being uploaded
+    pub fn append_ic(&mut self, points: &[[u8; 64]]) -> Result<()> {
+        require!(
+            self.status == VerifyingKeyStatus::Uploading,
+            AshbornError::VerifyingKeyLocked
+        );
+        require!(
+            !points.is_empty() && self.ic.len() + points.len() <= self.circuit.n_public() + 1,
+            AshbornError::InvalidVerifyingKey
+        );
+        self.ic.extend_from_slice(points);
+        Ok(())
+    }
+
+    /// Check this key is complete and has never been active
+    pub fn check_activatable(&self) -> Result<()> {
+        require!(
+            self.status == VerifyingKeyStatus::Uploading,
+            AshbornError::VerifyingKeyLocked
+        );
+        require!(self.is_complete(), AshbornError::InvalidVerifyingKey);
+        Ok(())
+    }
+
+    /// Check this is the active, complete key for `circuit`
+    pub fn check_active(&self, circuit: CircuitId) -> Result<()> {
+        require!(self.circuit == circuit, AshbornError::InvalidVerifyingKey);
+        require!(
+            self.status == VerifyingKeyStatus::Active,
+            AshbornError::InactiveVerifyingKey
+        );
+        require!(self.is_complete(), AshbornError::InvalidVerifyingKey);
+        Ok(())
+    }
+}
+
+/// Which verifying key version is active for each circuit
+#[account]
+pub struct VerifyingKeyRegistry {
+    /// Account layout version
+    pub version: u8,
+
+    /// Active version per circuit, indexed by `CircuitId` (0 = none)
+    pub active_versions: [u32; CircuitId::COUNT],
+
+    /// Bump seed
+    pub bump: u8,
+}
+
+impl VerifyingKeyRegistry {
+    pub const SIZE: usize = 8 + // discriminator
+        1 +  // version
+        4 * CircuitId::COUNT + // active_versions
+        1;   // bump
+
+    /// Current account layout version
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Whether this registry is on the current layout
+    pub fn is_current(&self) -> bool {
+        self.version == Self::CURRENT_VERSION
+    }
+
+    /// Active version for `circuit`, if any
+    pub fn active_version(&self, circuit: CircuitId) -> Option<u32> {
+        match self.active_versions[circuit as usize] {
+            0 => None,
+            version => Some(version),
+        }
+    }
+
+    /// Make `key` the active key for its circuit, retiring `previous`
+    ///
+    /// `previous` must be the circuit's active key if it has one.
+    /// Returns the retired version.
+    pub fn activate(
+        &mut self,
+        key: &mut VerifyingKey,
+        previous: Option<&mut VerifyingKey>,
+    ) -> Result<Option<u32>> {
+        key.check_activatable()?;
+
+        let circuit = key.circuit;
+        let retired = match (self.active_version(circuit), previous) {
+            (None, None) => None,
+            (Some(active), Some(previous)) => {
+                require!(
+                    previous.circuit == circuit && previous.version == active,
+                    AshbornError::InvalidVerifyingKey
+                );
+                previous.status = VerifyingKeyStatus::Retired;
+                Some(active)
+            }
+            _ => return err!(AshbornError::InvalidVerifyingKey),
+        };
+
+        key.status = VerifyingKeyStatus::Active;
+        self.active_versions[circuit as usize] = key.version;
+        Ok(retired)
+    }
+}
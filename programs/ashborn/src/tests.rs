@@ -2,52 +2,77 @@
 //!
 //! Tests for on-chain program logic
 
-use anchor_lang::prelude::*;
-use solana_program_test::*;
-use solana_sdk::{
-    signature::{Keypair, Signer},
-    transaction::Transaction,
-};
-
-// Test Poseidon hashing
+// Test Poseidon hashing against circomlib vectors
 #[cfg(test)]
 mod poseidon_tests {
-    use super::*;
+    use crate::zk::{create_commitment, generate_nullifier, poseidon_hash_2};
 
-    #[test]
-    fn test_poseidon_deterministic() {
-        // Same inputs should produce same output
-        let left = [1u8; 32];
-        let right = [2u8; 32];
+    /// Big-endian field element from a small integer
+    fn fe(value: u8) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[31] = value;
+        bytes
+    }
 
-        // In actual implementation, call poseidon_hash_2
-        // For now, verify the concept
-        assert_eq!(left.len(), 32);
-        assert_eq!(right.len(), 32);
+    fn hex(s: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    // circomlibjs: poseidon([1, 2])
+    const POSEIDON_1_2: &str = "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a";
+
+    // circomlibjs: poseidon([0, 0])
+    const POSEIDON_0_0: &str = "2098f5fb9e239eab3ceac3f27b81e481dc3124d55ffed523a839ee8446b64864";
+
+    #[test]
+    fn test_poseidon_matches_circomlib() {
+        assert_eq!(poseidon_hash_2(&fe(1), &fe(2)).unwrap(), hex(POSEIDON_1_2));
+        assert_eq!(poseidon_hash_2(&fe(0), &fe(0)).unwrap(), hex(POSEIDON_0_0));
     }
 
     #[test]
     fn test_poseidon_different_inputs() {
-        let a1 = [1u8; 32];
-        let a2 = [2u8; 32];
+        let h1 = poseidon_hash_2(&fe(1), &fe(2)).unwrap();
+        let h2 = poseidon_hash_2(&fe(2), &fe(1)).unwrap();
+
+        // Order matters (left/right children are distinct)
+        assert_ne!(h1, h2);
+    }
 
-        // Different inputs should produce different outputs
-        assert_ne!(a1, a2);
+    #[test]
+    fn test_commitment_matches_circuit() {
+        // shield.circom: commitment = Poseidon(amount, blinding)
+        assert_eq!(create_commitment(1, &fe(2)).unwrap(), hex(POSEIDON_1_2));
+    }
+
+    #[test]
+    fn test_nullifier_matches_circuit() {
+        // transfer.circom: nullifier = Poseidon(nullifierSecret, noteIndex)
+        assert_eq!(generate_nullifier(&fe(1), 2).unwrap(), hex(POSEIDON_1_2));
+    }
+
+    #[test]
+    fn test_empty_tree_level_one() {
+        // The first non-leaf zero hash is Poseidon(0, 0)
+        let zeros = crate::state::zero_hashes().unwrap();
+        assert_eq!(zeros[1], hex(POSEIDON_0_0));
     }
 }
 
 // Test commitment creation
 #[cfg(test)]
 mod commitment_tests {
-    use super::*;
-
     #[test]
     fn test_commitment_hides_amount() {
         let amount1 = 1_000_000_000u64; // 1 SOL
         let amount2 = 100_000_000_000u64; // 100 SOL
 
         // Both should produce 32-byte commitments
-        let blinding = [42u8; 32];
+        let _blinding = [42u8; 32];
         
         // Commitment is hash of (amount, blinding)
         let c1_bytes = amount1.to_le_bytes();
@@ -62,11 +87,119 @@ mod commitment_tests {
 // Test nullifier generation
 #[cfg(test)]
 mod nullifier_tests {
-    use super::*;
+    use crate::errors::AshbornError;
+    use crate::state::{NullifierSet, MAX_NULLIFIER_SET_SLOTS, NULLIFIER_SET_GROWTH};
+    use anchor_lang::solana_program::system_instruction::MAX_PERMITTED_DATA_LENGTH;
+
+    /// Nullifier in shard `prefix` with a distinguishing high byte
+    fn nullifier(high: u8, prefix: u8) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0] = high;
+        bytes[31] = prefix;
+        bytes
+    }
+
+    fn empty_set(prefix: u8, slots: usize) -> (NullifierSet, Vec<[u8; 32]>) {
+        let mut set: NullifierSet = bytemuck::Zeroable::zeroed();
+        let mut storage = vec![[1u8; 32]; slots];
+        set.initialize(prefix, 255, &mut storage);
+        (set, storage)
+    }
+
+    #[test]
+    fn test_nullifier_set_rejects_double_spend() {
+        let (mut set, mut slots) = empty_set(7, 4);
+
+        // Inserted out of order, kept sorted
+        for high in [5u8, 1, 3] {
+            set.insert(nullifier(high, 7), &mut slots).unwrap();
+        }
+        assert_eq!(set.count, 3);
+        assert_eq!(slots[..3], [nullifier(1, 7), nullifier(3, 7), nullifier(5, 7)]);
+        assert!(set.contains(&nullifier(3, 7), &slots));
+        assert!(!set.contains(&nullifier(4, 7), &slots));
+
+        let err = set.insert(nullifier(3, 7), &mut slots).unwrap_err();
+        assert_eq!(err, AshbornError::NullifierAlreadyUsed.into());
+        assert_eq!(set.count, 3);
+    }
+
+    #[test]
+    fn test_nullifier_set_shard_and_capacity() {
+        let (mut set, mut slots) = empty_set(7, 2);
+
+        let err = set.insert(nullifier(1, 8), &mut slots).unwrap_err();
+        assert_eq!(err, AshbornError::InvalidNullifier.into());
+
+        set.insert(nullifier(1, 7), &mut slots).unwrap();
+        set.insert(nullifier(2, 7), &mut slots).unwrap();
+        let err = set.insert(nullifier(3, 7), &mut slots).unwrap_err();
+        assert_eq!(err, AshbornError::NullifierSetFull.into());
+
+        // Growing only appends empty slots; existing entries stay put
+        slots.resize(2 + NULLIFIER_SET_GROWTH as usize, [0u8; 32]);
+        set.insert(nullifier(0, 7), &mut slots).unwrap();
+        assert_eq!(slots[..3], [nullifier(0, 7), nullifier(1, 7), nullifier(2, 7)]);
+    }
+
+    #[test]
+    fn test_nullifier_set_fits_creation_limits() {
+        // CPI account creation and a single realloc are both capped at 10 KiB
+        assert!(NullifierSet::space(NULLIFIER_SET_GROWTH) <= 10_240);
+        assert!(32 * NULLIFIER_SET_GROWTH as usize <= 10_240);
+    }
+
+    #[test]
+    fn test_nullifier_set_grows_only_when_nearly_full() {
+        let growth = NULLIFIER_SET_GROWTH as usize;
+        let (mut set, slots) = empty_set(7, growth);
+
+        let err = set.grown_len(&slots).unwrap_err();
+        assert_eq!(err, AshbornError::NullifierSetNotFull.into());
+
+        set.count = 1;
+        assert_eq!(set.grown_len(&slots).unwrap(), 2 * growth);
+    }
+
+    #[test]
+    fn test_nullifier_set_at_max_size() {
+        // The largest shard still fits an account; one more step would not
+        let max = MAX_NULLIFIER_SET_SLOTS as usize;
+        assert_eq!(max % NULLIFIER_SET_GROWTH as usize, 0);
+        assert!(NullifierSet::space(MAX_NULLIFIER_SET_SLOTS) as u64 <= MAX_PERMITTED_DATA_LENGTH);
+        assert!(
+            NullifierSet::space(MAX_NULLIFIER_SET_SLOTS + NULLIFIER_SET_GROWTH) as u64
+                > MAX_PERMITTED_DATA_LENGTH
+        );
+
+        // A max-size shard one short of full, holding 1..max-1 in its high bytes
+        let (mut set, mut slots) = empty_set(7, max);
+        for (i, slot) in slots[..max - 1].iter_mut().enumerate() {
+            slot[..4].copy_from_slice(&(i as u32 + 1).to_be_bytes());
+            slot[31] = 7;
+        }
+        set.count = max as u32 - 1;
+
+        let err = set.grown_len(&slots).unwrap_err();
+        assert_eq!(err, AshbornError::NullifierSetAtMaxSize.into());
+
+        // The smallest nullifier shifts every other entry: the worst-case insert
+        set.insert(nullifier(0, 7), &mut slots).unwrap();
+        assert_eq!(set.count as usize, max);
+        assert_eq!(slots[0], nullifier(0, 7));
+        assert_eq!(slots[max - 1][..4], (max as u32 - 1).to_be_bytes());
+        let middle = slots[max / 2];
+        assert!(set.contains(&middle, &slots));
+
+        let mut last = [0xffu8; 32];
+        last[31] = 7;
+        let err = set.insert(last, &mut slots).unwrap_err();
+        assert_eq!(err, AshbornError::NullifierSetFull.into());
+    }
 
     #[test]
     fn test_nullifier_unique_per_index() {
-        let secret = [99u8; 32];
+        let _secret = [99u8; 32];
 
         // Different indices should produce different nullifiers
         let index0 = 0u64;
@@ -166,7 +299,13 @@ mod timing_tests {
 // Test Merkle tree operations
 #[cfg(test)]
 mod merkle_tests {
-    const TREE_DEPTH: usize = 20;
+    use crate::state::{
+        zero_hashes, CommitmentTree, MAX_INITIAL_ROOT_HISTORY, MAX_ROOT_HISTORY,
+        MAX_ROOT_HISTORY_GROWTH, MIN_ROOT_HISTORY, TREE_CAPACITY, TREE_DEPTH,
+    };
+    use crate::errors::AshbornError;
+    use crate::zk::poseidon_hash_2;
+    use anchor_lang::prelude::Pubkey;
 
     #[test]
     fn test_tree_capacity() {
@@ -177,7 +316,7 @@ mod merkle_tests {
     #[test]
     fn test_merkle_path_length() {
         // Path should have TREE_DEPTH siblings
-        let path: Vec<[u8; 32]> = vec![[0u8; 32]; TREE_DEPTH];
+        let path = [[0u8; 32]; TREE_DEPTH];
         assert_eq!(path.len(), 20);
     }
 
@@ -196,6 +335,208 @@ mod merkle_tests {
 
         assert_eq!(directions, vec![1, 0, 1]); // r, l, r
     }
+
+    /// Full recomputation: hash level by level, padding with empty-subtree roots
+    fn full_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let zeros = zero_hashes().unwrap();
+        let mut level = leaves.to_vec();
+
+        for zero in zeros.iter() {
+            if level.len() % 2 == 1 {
+                level.push(*zero);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| poseidon_hash_2(&pair[0], &pair[1]).unwrap())
+                .collect();
+        }
+
+        level[0]
+    }
+
+    fn history() -> Vec<[u8; 32]> {
+        vec![[0u8; 32]; MIN_ROOT_HISTORY as usize]
+    }
+
+    #[test]
+    fn test_incremental_insert_matches_full_tree() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut roots).unwrap();
+
+        let leaves: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let index = commitments.insert_commitment(*leaf, &mut roots).unwrap();
+            assert_eq!(index, i as u64);
+
+            let expected = full_root(&leaves[..=i]);
+            assert_eq!(commitments.root, expected);
+        }
+        assert_eq!(commitments.next_index, 5);
+    }
+
+    #[test]
+    fn test_root_history_ring_buffer() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut roots).unwrap();
+
+        let mut seen = vec![commitments.root];
+        for i in 0..MIN_ROOT_HISTORY {
+            let mut leaf = [0u8; 32];
+            leaf[28..].copy_from_slice(&(i + 1).to_be_bytes());
+            commitments.insert_commitment(leaf, &mut roots).unwrap();
+            seen.push(commitments.root);
+        }
+
+        // The buffer wrapped once: the empty root was overwritten
+        assert_eq!(commitments.root_history_head, 0);
+        assert!(!commitments.is_valid_root(&seen[0], &roots));
+        assert!(seen[1..].iter().all(|root| commitments.is_valid_root(root, &roots)));
+
+        // Unused or zeroed slots never validate
+        assert!(!commitments.is_valid_root(&[0u8; 32], &roots));
+    }
+
+    #[test]
+    fn test_batch_append_matches_single_inserts() {
+        let leaves: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+
+        let mut single: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut single_roots = history();
+        single.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut single_roots).unwrap();
+        for leaf in &leaves {
+            single.insert_commitment(*leaf, &mut single_roots).unwrap();
+        }
+
+        let mut batched: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        batched.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut roots).unwrap();
+        batched.insert_commitment(leaves[0], &mut roots).unwrap();
+        let old_root = full_root(&leaves[..1]);
+        let head = batched.root_history_head;
+
+        let first = batched.append_commitments(&leaves[1..], &mut roots).unwrap();
+        assert_eq!(first, 1);
+        assert_eq!(batched.root, single.root);
+        assert_eq!(batched.next_index, 5);
+
+        // One history entry for the whole batch
+        assert_eq!(batched.root_history_head, head + 1);
+        // Proofs from before the batch stay valid through the root history
+        assert!(batched.is_valid_root(&old_root, &roots));
+    }
+
+    #[test]
+    fn test_batch_append_rejects_overflowing_shard() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut roots).unwrap();
+        commitments.next_index = TREE_CAPACITY - 1;
+        let root = commitments.root;
+
+        assert_eq!(commitments.remaining_capacity(), 1);
+        assert!(commitments.append_commitments(&[[1u8; 32], [2u8; 32]], &mut roots).is_err());
+        assert_eq!(commitments.root, root);
+
+        // An empty batch is a no-op
+        assert_eq!(commitments.append_commitments(&[], &mut roots).unwrap(), TREE_CAPACITY - 1);
+        assert_eq!(commitments.root, root);
+    }
+
+    #[test]
+    fn test_full_shard_rejects_inserts() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), Pubkey::default(), 3, 255, &mut roots).unwrap();
+
+        // Last free leaf, then the shard is full
+        commitments.next_index = TREE_CAPACITY - 1;
+        let leaf_index = commitments.insert_commitment([1u8; 32], &mut roots).unwrap();
+        assert_eq!(commitments.global_index(leaf_index), 3 * TREE_CAPACITY + TREE_CAPACITY - 1);
+        assert_eq!(CommitmentTree::shard_of(commitments.global_index(leaf_index)), 3);
+        assert!(commitments.is_full());
+        assert!(commitments.insert_commitment([2u8; 32], &mut roots).is_err());
+        assert_eq!(commitments.next_index, TREE_CAPACITY);
+
+        // A finished shard keeps its final root valid forever
+        let final_root = commitments.root;
+        assert!(commitments.is_valid_root(&final_root, &roots));
+    }
+
+    #[test]
+    fn test_shards_are_per_mint() {
+        let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let shard = |mint: &Pubkey| {
+            Pubkey::find_program_address(
+                &[b"commitment_tree", mint.as_ref(), &0u64.to_le_bytes()],
+                &crate::ID,
+            )
+            .0
+        };
+        assert_ne!(shard(&usdc), shard(&sol));
+
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), usdc, 0, 255, &mut roots).unwrap();
+        assert_eq!(commitments.mint, usdc);
+    }
+
+    #[test]
+    fn test_root_history_bounds() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+
+        let mut too_short = vec![[0u8; 32]; MIN_ROOT_HISTORY as usize - 1];
+        assert!(commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut too_short).is_err());
+
+        let mut too_long = vec![[0u8; 32]; MAX_INITIAL_ROOT_HISTORY as usize + 1];
+        assert!(commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut too_long).is_err());
+
+        // Largest new tree still fits a CPI-created account, and each growth
+        // step fits one instruction's realloc allowance
+        assert!(CommitmentTree::space(MAX_INITIAL_ROOT_HISTORY) <= 10_240);
+        assert!(32 * MAX_ROOT_HISTORY_GROWTH as usize <= 10_240);
+    }
+
+    #[test]
+    fn test_grown_root_history_keeps_every_root() {
+        let mut commitments: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = history();
+        commitments.initialize(Pubkey::default(), Pubkey::default(), 0, 255, &mut roots).unwrap();
+
+        // Wrap the ring part-way round before growing it
+        let mut seen = vec![commitments.root];
+        let insert = |commitments: &mut CommitmentTree, roots: &mut Vec<[u8; 32]>, seen: &mut Vec<[u8; 32]>| {
+            let mut leaf = [0u8; 32];
+            leaf[24..].copy_from_slice(&(seen.len() as u64).to_be_bytes());
+            commitments.insert_commitment(leaf, roots).unwrap();
+            seen.push(commitments.root);
+        };
+        for _ in 0..40 {
+            insert(&mut commitments, &mut roots, &mut seen);
+        }
+
+        let old_len = MIN_ROOT_HISTORY as usize;
+        roots.resize(2 * old_len, [7u8; 32]);
+        commitments.extend_root_history(&mut roots).unwrap();
+        assert_eq!(commitments.root_history_len, 2 * old_len as u32);
+        assert!(seen[seen.len() - old_len..].iter().all(|root| commitments.is_valid_root(root, &roots)));
+        assert!(!commitments.is_valid_root(&[7u8; 32], &roots));
+
+        // The new slots fill before any root from before the growth is dropped
+        for _ in 0..old_len {
+            insert(&mut commitments, &mut roots, &mut seen);
+        }
+        let window = seen[seen.len() - 2 * old_len..].to_vec();
+        assert!(window.iter().all(|root| commitments.is_valid_root(root, &roots)));
+        insert(&mut commitments, &mut roots, &mut seen);
+        assert!(!commitments.is_valid_root(&window[0], &roots));
+
+        // Never past the maximum
+        roots.resize(MAX_ROOT_HISTORY as usize + 1, [0u8; 32]);
+        let err = commitments.extend_root_history(&mut roots).unwrap_err();
+        assert_eq!(err, AshbornError::InvalidRootHistoryLength.into());
+    }
 }
 
 // Test vault state
@@ -244,8 +585,6 @@ mod vault_tests {
 // Test PDA derivation
 #[cfg(test)]
 mod pda_tests {
-    use solana_sdk::pubkey::Pubkey;
-
     #[test]
     fn test_vault_pda_seed() {
         let seed = b"shadow_vault";
@@ -272,8 +611,8 @@ mod proof_tests {
 
     #[test]
     fn test_proof_size_validation() {
-        let valid_proof = vec![0u8; MIN_PROOF_SIZE];
-        let invalid_proof = vec![0u8; 10];
+        let valid_proof = [0u8; MIN_PROOF_SIZE];
+        let invalid_proof = [0u8; 10];
 
         assert!(valid_proof.len() >= MIN_PROOF_SIZE);
         assert!(invalid_proof.len() < MIN_PROOF_SIZE);
@@ -291,8 +630,6 @@ mod proof_tests {
 // Integration-style tests
 #[cfg(test)]
 mod integration_tests {
-    use super::*;
-
     #[test]
     fn test_full_shield_flow() {
         // 1. Generate blinding
@@ -358,3 +695,670 @@ mod integration_tests {
         assert_eq!(proof.len(), 256);
     }
 }
+
+// Test protocol bring-up
+#[cfg(test)]
+mod protocol_tests {
+    use crate::state::{
+        CommitmentTree, Denomination, ProtocolState, MIN_ROOT_HISTORY, TREE_DEPTH,
+    };
+    use anchor_lang::prelude::Pubkey;
+
+    fn empty_commitment_tree() -> CommitmentTree {
+        let mut tree: CommitmentTree = bytemuck::Zeroable::zeroed();
+        tree.next_index = 7;
+        tree.filled_subtrees = [[1u8; 32]; TREE_DEPTH];
+        tree.zeros = [[1u8; 32]; TREE_DEPTH];
+        tree.root_history_head = 9;
+        tree
+    }
+
+    #[test]
+    fn test_tree_initializes_from_dirty_account() {
+        let authority = Pubkey::new_unique();
+        let mut commitments = empty_commitment_tree();
+        let mut commitment_roots = vec![[1u8; 32]; MIN_ROOT_HISTORY as usize];
+
+        commitments.initialize(authority, Pubkey::default(), 0, 254, &mut commitment_roots).unwrap();
+
+        assert_ne!(commitments.root, [0u8; 32]);
+        assert_eq!(commitments.next_index, 0);
+        assert_eq!(commitments.authority, authority);
+        assert_eq!(commitments.bump, 254);
+        // Fresh trees are stamped with the current layout
+        assert!(commitments.is_current());
+        assert_eq!(commitments.root_history_len, MIN_ROOT_HISTORY);
+        assert_eq!(commitments.root_history_head, 0);
+        assert!(commitments.is_valid_root(&commitment_roots[0], &commitment_roots));
+        assert!(!commitments.is_valid_root(&[1u8; 32], &commitment_roots));
+    }
+
+    fn protocol_state(admin: Pubkey, guardian: Pubkey) -> ProtocolState {
+        ProtocolState {
+            version: ProtocolState::CURRENT_VERSION,
+            admin,
+            total_shielded: 0,
+            tx_count: 0,
+            fee_bps: 0,
+            fee_recipient: admin,
+            paused: false,
+            min_privacy_delay: ProtocolState::DEFAULT_DELAY,
+            bump: 0,
+            pending_admin: Pubkey::default(),
+            guardian,
+            change_nonce: 0,
+            denomination_delays: [0; Denomination::COUNT],
+        }
+    }
+
+    #[test]
+    fn test_guardian_can_only_pause() {
+        let admin = Pubkey::new_unique();
+        let guardian = Pubkey::new_unique();
+        let state = protocol_state(admin, guardian);
+
+        assert!(state.can_pause(&admin));
+        assert!(state.can_pause(&guardian));
+        assert!(!state.can_pause(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_privacy_delay_from_config() {
+        let mut state = protocol_state(Pubkey::new_unique(), Pubkey::new_unique());
+        let now = 1_000i64;
+
+        // Localnet pools can run with a short delay
+        state.min_privacy_delay = 60;
+        assert_eq!(state.unshield_after(now, None).unwrap(), now + 60);
+        assert_eq!(state.unshield_after(now, Some(Denomination::Tier1)).unwrap(), now + 60);
+
+        // Per-tier override lengthens the delay for that tier only
+        state.denomination_delays[Denomination::Tier5 as usize] = 3_600;
+        assert_eq!(state.privacy_delay(Some(Denomination::Tier5)), 3_600);
+        assert_eq!(state.privacy_delay(Some(Denomination::Tier4)), 60);
+
+        // An override never shortens the global minimum
+        state.denomination_delays[Denomination::Tier2 as usize] = 10;
+        assert_eq!(state.privacy_delay(Some(Denomination::Tier2)), 60);
+
+        assert!(state.unshield_after(i64::MAX, None).is_err());
+    }
+
+    #[test]
+    fn test_tvl_accounting() {
+        let mut state = protocol_state(Pubkey::new_unique(), Pubkey::new_unique());
+
+        state.record_shield(1_000_000_000).unwrap();
+        state.record_shield(100_000_000).unwrap();
+        state.record_tx().unwrap();
+        assert_eq!(state.total_shielded, 1_100_000_000);
+
+        state.record_unshield(1_000_000_000).unwrap();
+        assert_eq!(state.total_shielded, 100_000_000);
+        assert_eq!(state.tx_count, 4);
+
+        // Cannot unshield more than was ever shielded
+        assert!(state.record_unshield(100_000_001).is_err());
+        assert_eq!(state.total_shielded, 100_000_000);
+    }
+}
+
+// Test timelocked configuration changes
+#[cfg(test)]
+mod timelock_tests {
+    use crate::state::{CircuitId, ParameterChange, PendingChange, ProtocolState};
+    use anchor_lang::prelude::Pubkey;
+
+    fn pending(change: ParameterChange, eta: i64) -> PendingChange {
+        PendingChange {
+            version: PendingChange::CURRENT_VERSION,
+            id: 0,
+            change,
+            proposer: Pubkey::new_unique(),
+            queued_at: eta - PendingChange::MIN_DELAY,
+            eta,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_change_validation() {
+        assert!(ParameterChange::FeeBps(ProtocolState::MAX_FEE_BPS).validate().is_ok());
+        assert!(ParameterChange::FeeBps(ProtocolState::MAX_FEE_BPS + 1).validate().is_err());
+        assert!(ParameterChange::MinPrivacyDelay(0).validate().is_ok());
+        assert!(ParameterChange::MinPrivacyDelay(-1).validate().is_err());
+        assert!(ParameterChange::RateLimit {
+            max_deposits_per_epoch: 10,
+            epoch_duration_slots: 0,
+        }
+        .validate()
+        .is_err());
+        assert!(ParameterChange::ActivateVerifyingKey {
+            circuit: CircuitId::Shield,
+            version: 0,
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_timelock_window() {
+        let eta = 1_000_000i64;
+        let change = pending(ParameterChange::FeeBps(10), eta);
+
+        assert!(change.check_executable(eta - 1).is_err());
+        assert!(change.check_executable(eta).is_ok());
+        assert!(change.check_executable(eta + PendingChange::GRACE_PERIOD).is_ok());
+        assert!(change.check_executable(eta + PendingChange::GRACE_PERIOD + 1).is_err());
+    }
+}
+
+// Test per-mint pool caps and solvency
+#[cfg(test)]
+mod pool_tests {
+    use crate::state::{Denomination, PoolConfig, ShieldedNote};
+    use anchor_lang::prelude::Pubkey;
+
+    fn pool(max_tvl: u64) -> PoolConfig {
+        PoolConfig {
+            version: PoolConfig::CURRENT_VERSION,
+            mint: Pubkey::new_unique(),
+            max_tvl,
+            total_shielded: 0,
+            bump: 0,
+            commitment_tree: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn test_tvl_cap() {
+        let mut config = pool(2_000_000_000);
+
+        config.record_deposit(1_000_000_000).unwrap();
+        config.record_deposit(1_000_000_000).unwrap();
+        assert!(config.record_deposit(100_000_000).is_err());
+        assert_eq!(config.total_shielded, 2_000_000_000);
+    }
+
+    #[test]
+    fn test_solvency_invariant() {
+        let mut config = pool(u64::MAX);
+        config.record_deposit(1_000_000_000).unwrap();
+
+        // Pool holds exactly what it owes
+        assert!(config.check_solvency(1_000_000_000, 1_000_000_000).is_ok());
+
+        // Pool drained below what it owes
+        assert!(config.check_solvency(999_999_999, 100_000_000).is_err());
+
+        // Withdrawal larger than anything shielded
+        assert!(config.check_solvency(5_000_000_000, 1_000_000_001).is_err());
+
+        config.record_withdrawal(1_000_000_000).unwrap();
+        assert_eq!(config.total_shielded, 0);
+        assert!(config.record_withdrawal(1).is_err());
+    }
+
+    fn note(denomination_tier: u8) -> ShieldedNote {
+        ShieldedNote {
+            version: ShieldedNote::CURRENT_VERSION,
+            vault: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            commitment: [1u8; 32],
+            encrypted_amount: [0u8; 48],
+            index: 1,
+            leaf_index: 0,
+            denomination_tier,
+            spent: false,
+            created_at: 0,
+            unshield_after: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn test_unshield_amount_is_the_note_value() {
+        // A 0.1 SOL note in a pool that also holds other users' deposits
+        let mut config = pool(u64::MAX);
+        config.record_deposit(100_000_000_000).unwrap();
+        let small = note(Denomination::Tier1 as u8);
+
+        // Asking for more than the note is worth is rejected before any accounting
+        assert!(small.withdrawal_amount(100_000_000_000).is_err());
+        assert!(small.withdrawal_amount(u64::MAX).is_err());
+        assert!(small.withdrawal_amount(1).is_err());
+
+        let amount = small.withdrawal_amount(100_000_000).unwrap();
+        config.record_withdrawal(amount).unwrap();
+        assert_eq!(config.total_shielded, 99_900_000_000);
+
+        // Change notes have no public value; the withdraw proof binds the amount
+        let change = note(ShieldedNote::NO_DENOMINATION);
+        assert!(change.withdrawal_amount(0).is_err());
+        assert_eq!(change.withdrawal_amount(12_345).unwrap(), 12_345);
+    }
+}
+
+// Test account versioning and legacy layouts
+#[cfg(test)]
+mod migration_tests {
+    use crate::errors::AshbornError;
+    use crate::instructions::{layout_version, read_legacy, read_tree_v1, relayout_tree_v1, verify_note_address};
+    use crate::state::{
+        load_tree, CommitmentTree, CommitmentTreeV1, PoolConfig, PoolConfigV0, ProtocolState,
+        ProtocolStateV0, ProtocolStateV1, ShadowVault, ShieldedNote, ShieldedNoteV0, ShieldedNoteV1, ShieldedNoteV2,
+        MIN_ROOT_HISTORY,
+    };
+    use anchor_lang::prelude::*;
+    use anchor_lang::Discriminator;
+
+    /// Layout version of an account holding `data`
+    fn version_of(mut data: Vec<u8>, unversioned_size: usize) -> Result<u8> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+        layout_version(&info, &PoolConfig::DISCRIMINATOR, unversioned_size)
+    }
+
+    /// Layout version of a protocol state account holding `data`
+    fn version_of_state(mut data: Vec<u8>) -> u8 {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+        layout_version(&info, &ProtocolState::DISCRIMINATOR, ProtocolStateV0::SIZE).unwrap()
+    }
+
+    #[test]
+    fn test_vault_version_fits_reserved_space() {
+        // Pre-versioning vaults had 64 reserved bytes; the layout size must not change
+        assert_eq!(ShadowVault::SIZE, 8 + 32 + 1 + 4 + 32 + 48 + 48 + 8 + 8 + 64);
+    }
+
+    #[test]
+    fn test_legacy_note_upgrade() {
+        let legacy = ShieldedNoteV0 {
+            vault: Pubkey::new_unique(),
+            commitment: [7u8; 32],
+            encrypted_amount: [9u8; 48],
+            index: 3,
+            denomination_tier: 2,
+            spent: false,
+            created_at: 100,
+            unshield_after: 200,
+            bump: 254,
+        };
+        assert_eq!(legacy.try_to_vec().unwrap().len() + 8, ShieldedNoteV0::SIZE);
+
+        let v1 = legacy.clone().into_v1();
+        assert_eq!(v1.try_to_vec().unwrap().len() + 8, ShieldedNoteV1::SIZE);
+
+        let mint = Pubkey::new_unique();
+        let mut v2 = v1.into_v2(mint);
+        assert_eq!(v2.try_to_vec().unwrap().len() + 8, ShieldedNoteV2::SIZE);
+
+        // The commitment was re-appended at leaf 40 of the mint's tree; the
+        // note's PDA was derived from sequence number 5
+        v2.index = 40;
+        let note = v2.upgrade(5);
+        assert!(note.is_current());
+        assert_eq!(note.vault, legacy.vault);
+        assert_eq!(note.mint, mint);
+        assert_eq!(note.index, 5);
+        assert_eq!(note.leaf_index, 40);
+        assert_eq!(note.unshield_after, 200);
+        assert_eq!(note.try_to_vec().unwrap().len() + 8, ShieldedNote::SIZE);
+    }
+
+    #[test]
+    fn test_legacy_deposit_note_migrates_at_its_creation_seed() {
+        // shield_deposit before versioning: the vault's third note, whose
+        // commitment landed at leaf 17 of the shared tree
+        let vault = Pubkey::new_unique();
+        let note_count: u32 = 2;
+        let seq = note_count + 1;
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"shielded_note", vault.as_ref(), &seq.to_le_bytes()],
+            &crate::ID,
+        );
+        let legacy = ShieldedNoteV0 {
+            vault,
+            commitment: [7u8; 32],
+            encrypted_amount: [9u8; 48],
+            index: 17,
+            denomination_tier: 1,
+            spent: false,
+            created_at: 100,
+            unshield_after: 200,
+            bump,
+        };
+        let mut data = ShieldedNote::DISCRIMINATOR.to_vec();
+        data.extend(legacy.try_to_vec().unwrap());
+
+        let mut lamports = 0;
+        let info = AccountInfo::new(&address, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        assert_eq!(layout_version(&info, &ShieldedNote::DISCRIMINATOR, ShieldedNoteV0::SIZE).unwrap(), 0);
+        let read: ShieldedNoteV0 = read_legacy(&info, ShieldedNoteV0::SIZE).unwrap();
+
+        // The PDA comes from the 4-byte sequence number, not the stored leaf index
+        verify_note_address(&address, &vault, seq, read.bump).unwrap();
+        assert!(verify_note_address(&address, &vault, read.index as u32, read.bump).is_err());
+        assert!(verify_note_address(&address, &Pubkey::new_unique(), seq, read.bump).is_err());
+
+        let note = read.into_v1().into_v2(Pubkey::new_unique()).upgrade(seq);
+        assert!(note.is_current());
+        assert_eq!(note.commitment, [7u8; 32]);
+        // The spend paths re-derive the PDA from `index`
+        assert_eq!(note.index.to_le_bytes(), seq.to_le_bytes());
+        assert_eq!(note.leaf_index, 17);
+    }
+
+    #[test]
+    fn test_v1_commitment_tree_drops_its_changelog() {
+        // A shard with a few roots in its history, as v1 stored it
+        let mut tree: CommitmentTree = bytemuck::Zeroable::zeroed();
+        let mut roots = vec![[0u8; 32]; MIN_ROOT_HISTORY as usize];
+        let mint = Pubkey::new_unique();
+        tree.initialize(Pubkey::new_unique(), mint, 2, 251, &mut roots).unwrap();
+        for i in 1..=3u8 {
+            tree.insert_commitment([i; 32], &mut roots).unwrap();
+        }
+
+        let mut legacy: CommitmentTreeV1 = bytemuck::Zeroable::zeroed();
+        legacy.authority = tree.authority;
+        legacy.mint = mint;
+        legacy.root = tree.root;
+        legacy.filled_subtrees = tree.filled_subtrees;
+        legacy.zeros = tree.zeros;
+        legacy.changelog[0].root = [2u8; 32];
+        legacy.next_index = tree.next_index;
+        legacy.sequence_number = 3;
+        legacy.tree_id = 2;
+        legacy.root_history_len = tree.root_history_len;
+        legacy.root_history_head = tree.root_history_head;
+        legacy.changelog_head = 3;
+        legacy.depth = tree.depth;
+        legacy.bump = 251;
+        legacy.version = 1;
+
+        let mut data = CommitmentTree::DISCRIMINATOR.to_vec();
+        data.extend(bytemuck::bytes_of(&legacy));
+        data.extend(roots.iter().flatten());
+
+        // The v1 data can't be borrowed as a current tree
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        {
+            let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+            let loader = AccountLoader::<CommitmentTree>::try_from(&info).unwrap();
+            assert!(load_tree(&loader).is_err());
+        }
+
+        let read = read_tree_v1(&data).unwrap();
+        let new_size = relayout_tree_v1(&mut data, &read);
+        assert_eq!(new_size, CommitmentTree::space(MIN_ROOT_HISTORY));
+        data.truncate(new_size);
+
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        let loader = AccountLoader::<CommitmentTree>::try_from(&info).unwrap();
+        {
+            let (migrated, history) = load_tree(&loader).unwrap();
+            assert!(migrated.is_current());
+            assert_eq!(migrated.mint, mint);
+            assert_eq!(migrated.tree_id, 2);
+            assert_eq!(migrated.root, tree.root);
+            assert_eq!(migrated.next_index, 3);
+            assert_eq!(migrated.root_history_head, tree.root_history_head);
+            assert_eq!(&history[..], &roots[..]);
+        }
+
+        // Migrating again is refused
+        let err = read_tree_v1(&info.try_borrow_data().unwrap()).err().unwrap();
+        assert_eq!(err, AshbornError::AccountAlreadyMigrated.into());
+    }
+
+    #[test]
+    fn test_legacy_pool_config_upgrade() {
+        let legacy = PoolConfigV0 {
+            mint: Pubkey::new_unique(),
+            max_tvl: 1_000,
+            total_shielded: 400,
+            bump: 253,
+        };
+        assert_eq!(legacy.try_to_vec().unwrap().len() + 8, PoolConfigV0::SIZE);
+
+        let tree = Pubkey::new_unique();
+        let pool = legacy.clone().upgrade(tree);
+        assert_eq!(pool.mint, legacy.mint);
+        assert_eq!(pool.total_shielded, 400);
+        assert_eq!(pool.commitment_tree, tree);
+        assert_eq!(pool.try_to_vec().unwrap().len() + 8, PoolConfig::SIZE);
+    }
+
+    #[test]
+    fn test_pool_layout_detected_by_version() {
+        // A legacy mint whose first byte looks like a version is still v0
+        let legacy = PoolConfigV0 {
+            mint: Pubkey::new_from_array([PoolConfig::CURRENT_VERSION; 32]),
+            max_tvl: 1_000,
+            total_shielded: 400,
+            bump: 253,
+        };
+        let mut data = PoolConfig::DISCRIMINATOR.to_vec();
+        data.extend(legacy.try_to_vec().unwrap());
+        assert_eq!(version_of(data, PoolConfigV0::SIZE).unwrap(), 0);
+
+        let mut current = Vec::new();
+        legacy.upgrade(Pubkey::new_unique()).try_serialize(&mut current).unwrap();
+        assert_eq!(version_of(current.clone(), PoolConfigV0::SIZE).unwrap(), PoolConfig::CURRENT_VERSION);
+
+        // Any other discriminator is not a pool config at all
+        current[0] ^= 1;
+        assert!(version_of(current, PoolConfigV0::SIZE).is_err());
+    }
+
+    #[test]
+    fn test_legacy_protocol_state_upgrade() {
+        let admin = Pubkey::new_unique();
+        let legacy = ProtocolStateV0 {
+            admin,
+            total_shielded: 5,
+            tx_count: 6,
+            fee_bps: 30,
+            fee_recipient: Pubkey::new_unique(),
+            paused: true,
+            commitment_tree: Pubkey::new_unique(),
+            nullifier_tree: Pubkey::new_unique(),
+            min_privacy_delay: 60,
+            bump: 250,
+        };
+        assert_eq!(legacy.try_to_vec().unwrap().len() + 8, ProtocolStateV0::SIZE);
+
+        let v1 = legacy.into_v1();
+        assert_eq!(v1.try_to_vec().unwrap().len() + 8, ProtocolStateV1::SIZE);
+
+        let mut data = ProtocolState::DISCRIMINATOR.to_vec();
+        data.extend(v1.try_to_vec().unwrap());
+        assert_eq!(version_of_state(data), 1);
+
+        // The unused tree keys are dropped
+        let state = v1.upgrade();
+        assert!(state.is_current());
+        assert!(state.paused);
+        assert_eq!(state.fee_bps, 30);
+        // The old admin keeps the pause switch until a guardian is set
+        assert_eq!(state.guardian, admin);
+        assert_eq!(state.pending_admin, Pubkey::default());
+        assert_eq!(state.try_to_vec().unwrap().len() + 8, ProtocolState::SIZE);
+    }
+}
+
+// Test event encoding for indexers
+#[cfg(test)]
+mod event_tests {
+    use crate::events::{ChangeExecuted, CommitmentInserted, ConfigChange, ProtocolConfigChanged};
+    use crate::state::{CircuitId, ParameterChange};
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::{AnchorDeserialize, Discriminator, Event};
+
+    #[test]
+    fn test_commitment_inserted_round_trips() {
+        let event = CommitmentInserted {
+            tree: Pubkey::new_unique(),
+            index: (3 << 20) | 7,
+            leaf: [5u8; 32],
+            new_root: [9u8; 32],
+        };
+
+        // Indexers read the discriminator, then the full leaf and root
+        let data = event.data();
+        assert_eq!(data[..8], CommitmentInserted::DISCRIMINATOR);
+        let decoded = CommitmentInserted::deserialize(&mut &data[8..]).unwrap();
+        assert_eq!(decoded.tree, event.tree);
+        assert_eq!(decoded.index, event.index);
+        assert_eq!(decoded.leaf, event.leaf);
+        assert_eq!(decoded.new_root, event.new_root);
+    }
+
+    #[test]
+    fn test_config_change_round_trips() {
+        let event = ProtocolConfigChanged {
+            authority: Pubkey::new_unique(),
+            change: ConfigChange::VerifyingKeyCreated {
+                circuit: CircuitId::Transfer,
+                version: 2,
+            },
+        };
+
+        let data = event.data();
+        let decoded = ProtocolConfigChanged::deserialize(&mut &data[8..]).unwrap();
+        assert_eq!(decoded.authority, event.authority);
+        assert_eq!(decoded.change, event.change);
+    }
+
+    #[test]
+    fn test_change_executed_round_trips() {
+        let event = ChangeExecuted {
+            id: 4,
+            change: ParameterChange::FeeBps(25),
+            executed_at: 1_700_000_000,
+        };
+
+        let data = event.data();
+        assert_eq!(data[..8], ChangeExecuted::DISCRIMINATOR);
+        let decoded = ChangeExecuted::deserialize(&mut &data[8..]).unwrap();
+        assert_eq!(decoded.id, 4);
+        assert_eq!(decoded.change, event.change);
+        assert_eq!(decoded.executed_at, event.executed_at);
+    }
+}
+
+// Test verifying key upload and activation bookkeeping
+#[cfg(test)]
+mod verifying_key_tests {
+    use crate::state::{CircuitId, VerifyingKey, VerifyingKeyRegistry, VerifyingKeyStatus};
+    use anchor_lang::AnchorSerialize;
+
+    fn uploading(circuit: CircuitId) -> VerifyingKey {
+        VerifyingKey {
+            layout_version: VerifyingKey::CURRENT_LAYOUT_VERSION,
+            circuit,
+            version: 1,
+            status: VerifyingKeyStatus::Uploading,
+            alpha_g1: [0u8; 64],
+            beta_g2: [0u8; 128],
+            gamma_g2: [0u8; 128],
+            delta_g2: [0u8; 128],
+            bump: 0,
+            ic: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ic_is_uploaded_in_chunks() {
+        // Transfer has 5 public inputs, so 6 IC points
+        let mut key = uploading(CircuitId::Transfer);
+        key.append_ic(&[[1u8; 64]; 4]).unwrap();
+        assert!(!key.is_complete());
+
+        // Overfilling and empty chunks are rejected
+        assert!(key.append_ic(&[[2u8; 64]; 3]).is_err());
+        assert!(key.append_ic(&[]).is_err());
+
+        key.append_ic(&[[2u8; 64]; 2]).unwrap();
+        assert!(key.is_complete());
+        assert!(key.append_ic(&[[3u8; 64]]).is_err());
+    }
+
+    #[test]
+    fn test_active_key_is_immutable() {
+        let mut key = uploading(CircuitId::Shield);
+        key.append_ic(&[[1u8; 64]; 3]).unwrap();
+        assert!(key.check_active(CircuitId::Shield).is_err());
+
+        key.status = VerifyingKeyStatus::Active;
+        assert!(key.check_active(CircuitId::Shield).is_ok());
+        assert!(key.append_ic(&[[1u8; 64]]).is_err());
+    }
+
+    #[test]
+    fn test_activation_retires_previous_key() {
+        let mut registry = VerifyingKeyRegistry {
+            version: VerifyingKeyRegistry::CURRENT_VERSION,
+            active_versions: [0; CircuitId::COUNT],
+            bump: 0,
+        };
+
+        // Incomplete keys can't be activated, or queued for activation
+        let mut first = uploading(CircuitId::Shield);
+        assert!(first.check_activatable().is_err());
+        assert!(registry.activate(&mut first, None).is_err());
+
+        first.append_ic(&[[1u8; 64]; 3]).unwrap();
+        assert_eq!(registry.activate(&mut first, None).unwrap(), None);
+        assert_eq!(registry.active_version(CircuitId::Shield), Some(1));
+        assert!(first.check_activatable().is_err());
+
+        let mut second = uploading(CircuitId::Shield);
+        second.version = 2;
+        second.append_ic(&[[2u8; 64]; 3]).unwrap();
+
+        // The circuit's active key must be passed in to be retired
+        assert!(registry.activate(&mut second, None).is_err());
+        assert_eq!(registry.activate(&mut second, Some(&mut first)).unwrap(), Some(1));
+        assert_eq!(first.status, VerifyingKeyStatus::Retired);
+        assert_eq!(second.status, VerifyingKeyStatus::Active);
+        assert_eq!(registry.active_version(CircuitId::Shield), Some(2));
+    }
+
+    #[test]
+    fn test_space_fits_complete_key() {
+        for circuit in [
+            CircuitId::Shield,
+            CircuitId::Transfer,
+            CircuitId::Range,
+            CircuitId::Ownership,
+        ] {
+            let mut key = uploading(circuit);
+            key.ic = vec![[0u8; 64]; circuit.n_public() + 1];
+            assert_eq!(8 + key.try_to_vec().unwrap().len(), VerifyingKey::space(circuit));
+        }
+    }
+
+    #[test]
+    fn test_registry_tracks_one_version_per_circuit() {
+        let mut registry = VerifyingKeyRegistry {
+            version: VerifyingKeyRegistry::CURRENT_VERSION,
+            active_versions: [0; CircuitId::COUNT],
+            bump: 0,
+        };
+        assert_eq!(registry.active_version(CircuitId::Range), None);
+
+        registry.active_versions[CircuitId::Range as usize] = 3;
+        assert_eq!(registry.active_version(CircuitId::Range), Some(3));
+        assert_eq!(registry.active_version(CircuitId::Ownership), None);
+        assert_eq!(
+            8 + registry.try_to_vec().unwrap().len(),
+            VerifyingKeyRegistry::SIZE
+        );
+    }
+}
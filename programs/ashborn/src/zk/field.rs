@@ -0,0 +1,79 @@
+//! BN254 scalar field encoding for public inputs
+//!
+//! The alt_bn128 syscalls, snarkjs and `convert_vk.js` all use 32-byte
+//! big-endian field elements. Every public input handed to a verifier goes
+//! through here, so a u64 is always encoded the way the circuit sees it and
+//! a 32-byte value is only accepted in canonical form (`< r`): a value and
+//! its reduction mod r would otherwise be two encodings of the same signal.
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AshbornError;
+
+/// BN254 scalar field modulus r (big-endian)
+pub const SCALAR_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29,
+    0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91,
+    0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// Encode a u64 as a big-endian field element (always canonical)
+pub fn u64_to_field(value: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+/// Whether big-endian `bytes` is strictly below r
+pub fn is_canonical(bytes: &[u8; 32]) -> bool {
+    // Big-endian byte arrays compare like the integers they encode
+    *bytes < SCALAR_MODULUS
+}
+
+/// Encode an account address as a field element
+///
+/// Addresses span the full 256 bits, so the most significant byte is
+/// cleared; circuits take the address in this form.
+pub fn pubkey_to_field(key: &Pubkey) -> [u8; 32] {
+    let mut bytes = key.to_bytes();
+    bytes[0] = 0;
+    bytes
+}
+
+/// Accept a big-endian field element only in canonical form
+pub fn to_field(bytes: &[u8; 32]) -> Result<[u8; 32]> {
+    require!(is_canonical(bytes), AshbornError::NonCanonicalFieldElement);
+    Ok(*bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u64_is_big_endian() {
+        let bytes = u64_to_field(0x0102);
+        assert_eq!(bytes[30..], [0x01, 0x02]);
+        assert!(bytes[..30].iter().all(|&b| b == 0));
+        assert!(is_canonical(&u64_to_field(u64::MAX)));
+    }
+
+    #[test]
+    fn test_pubkey_drops_top_byte() {
+        let bytes = pubkey_to_field(&Pubkey::new_from_array([0xa5; 32]));
+        assert_eq!(bytes[0], 0);
+        assert!(bytes[1..].iter().all(|&b| b == 0xa5));
+        assert!(is_canonical(&bytes));
+    }
+
+    #[test]
+    fn test_rejects_values_at_or_above_modulus() {
+        let mut below = SCALAR_MODULUS;
+        below[31] -= 1;
+        assert_eq!(to_field(&below).unwrap(), below);
+
+        assert!(to_field(&SCALAR_MODULUS).is_err());
+        assert!(to_field(&[0xff; 32]).is_err());
+    }
+}
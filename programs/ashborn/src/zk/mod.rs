@@ -2,11 +2,12 @@
 //!
 //! Real cryptographic verification using Solana's Alt_bn128 syscalls
 
+pub mod field;
 pub mod verifier;
 pub mod poseidon;
-pub mod vkeys;
 pub mod vkeys_generated;  // Real VKs from Circom circuits
 
+pub use field::{pubkey_to_field, to_field, u64_to_field};
 pub use verifier::*;
 pub use poseidon::{poseidon_hash_2, create_commitment, generate_nullifier};
 // vkeys are accessed via crate::zk::vkeys_generated::*
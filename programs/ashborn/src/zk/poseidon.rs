@@ -1,44 +1,36 @@
-//! Poseidon-compatible hash for ZK-friendly commitments
+//! Poseidon hash for ZK-friendly commitments
 //!
-//! Using Keccak256 (Solana syscall) as a production-safe alternative
-//! until Solana adds native Poseidon syscalls.
+//! BN254 Poseidon with circomlib parameters (x^5 S-box, width = inputs + 1),
+//! so on-chain tree roots, commitments and nullifiers match what
+//! `shield.circom`, `transfer.circom` and `range.circom` compute.
 //!
-//! NOTE: For ZK circuits, you would use actual Poseidon client-side.
-//! This on-chain implementation uses keccak256 which:
-//! - Is cryptographically secure
-//! - Works within BPF stack limits
-//! - Has native Solana syscall support (fast, cheap)
+//! On-chain this is the `sol_poseidon` syscall; off-chain `solana_program`
+//! falls back to light-poseidon with the same parameters. All values are
+//! 32-byte big-endian BN254 scalars.
 
-use anchor_lang::solana_program::keccak;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::poseidon::{hashv, Endianness, Parameters};
 
-/// Hash two 32-byte inputs using keccak256
-/// 
-/// This is the on-chain implementation. ZK proofs are generated
-/// client-side using actual Poseidon, and verified structurally here.
-pub fn poseidon_hash_2(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    let mut input = [0u8; 64];
-    input[..32].copy_from_slice(left);
-    input[32..].copy_from_slice(right);
-    keccak::hash(&input).to_bytes()
-}
+use crate::errors::AshbornError;
+use crate::zk::field::u64_to_field;
 
-/// Create a commitment: C = Hash(amount, blinding)
-pub fn create_commitment(amount: u64, blinding: &[u8; 32]) -> [u8; 32] {
-    let amount_bytes = amount_to_bytes(amount);
-    poseidon_hash_2(&amount_bytes, blinding)
+/// Hash two field elements: circomlib `Poseidon(2)`
+///
+/// Fails if either input is not a canonical field element.
+pub fn poseidon_hash_2(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left, right])
+        .map(|hash| hash.to_bytes())
+        .map_err(|_| error!(AshbornError::HashError))
 }
 
-/// Generate nullifier: N = Hash(secret, note_index)
-pub fn generate_nullifier(secret: &[u8; 32], note_index: u64) -> [u8; 32] {
-    let index_bytes = amount_to_bytes(note_index);
-    poseidon_hash_2(secret, &index_bytes)
+/// Create a commitment: C = Poseidon(amount, blinding)
+pub fn create_commitment(amount: u64, blinding: &[u8; 32]) -> Result<[u8; 32]> {
+    poseidon_hash_2(&u64_to_field(amount), blinding)
 }
 
-/// Convert u64 to 32-byte array (little-endian, padded)
-fn amount_to_bytes(amount: u64) -> [u8; 32] {
-    let mut bytes = [0u8; 32];
-    bytes[..8].copy_from_slice(&amount.to_le_bytes());
-    bytes
+/// Generate nullifier: N = Poseidon(secret, note_index)
+pub fn generate_nullifier(secret: &[u8; 32], note_index: u64) -> Result<[u8; 32]> {
+    poseidon_hash_2(secret, &u64_to_field(note_index))
 }
 
 #[cfg(test)]
@@ -50,8 +42,8 @@ mod tests {
         let amount = 1_000_000_000u64; // 1 SOL
         let blinding = [42u8; 32];
         
-        let c1 = create_commitment(amount, &blinding);
-        let c2 = create_commitment(amount, &blinding);
+        let c1 = create_commitment(amount, &blinding).unwrap();
+        let c2 = create_commitment(amount, &blinding).unwrap();
         
         assert_eq!(c1, c2);
     }
@@ -62,20 +54,26 @@ mod tests {
         let blinding1 = [1u8; 32];
         let blinding2 = [2u8; 32];
         
-        let c1 = create_commitment(amount, &blinding1);
-        let c2 = create_commitment(amount, &blinding2);
+        let c1 = create_commitment(amount, &blinding1).unwrap();
+        let c2 = create_commitment(amount, &blinding2).unwrap();
         
         assert_ne!(c1, c2);
     }
 
     #[test]
     fn test_nullifier_derivation() {
-        let secret = [99u8; 32];
+        let secret = [9u8; 32];
         let index = 0u64;
         
-        let n1 = generate_nullifier(&secret, index);
-        let n2 = generate_nullifier(&secret, index + 1);
+        let n1 = generate_nullifier(&secret, index).unwrap();
+        let n2 = generate_nullifier(&secret, index + 1).unwrap();
         
         assert_ne!(n1, n2);
     }
+
+    #[test]
+    fn test_rejects_non_field_element() {
+        // 0xff.. is above the BN254 scalar modulus
+        assert!(poseidon_hash_2(&[0xff; 32], &[0u8; 32]).is_err());
+    }
 }
@@ -4,10 +4,23 @@
 //! This is REAL verification, not mocked.
 //!
 //! The groth16-solana crate handles the heavy lifting of pairing math.
+//!
+//! Public inputs are encoded with `zk::field`: big-endian, canonical BN254
+//! scalars, exactly as snarkjs emits them. Non-canonical inputs are an error.
+//!
+//! Verifying keys are loaded from the circuit's active `VerifyingKey`
+//! account, passed in by the instruction.
+//!
+//! Builds with the `demo` feature replace the pairing check with a
+//! structural check (`verify_proof_structure`). That is the only way to
+//! reach it, and `lib.rs` gives `demo` builds their own devnet program id,
+//! so a demo binary deployed at the production address fails Anchor's
+//! program id check on every instruction.
 
 use anchor_lang::prelude::*;
-// Using real generated VKs from Circom circuits
-use crate::zk::vkeys_generated::{DEMO_MODE, transfer_vk, shield_vk};
+use crate::state::{CircuitId, VerifyingKey};
+use crate::zk::field::{pubkey_to_field, to_field, u64_to_field};
+use crate::zk::poseidon::create_commitment;
 
 /// Groth16 proof size: A (64) + B (128) + C (64) = 256 bytes
 pub const PROOF_SIZE: usize = 256;
@@ -16,10 +29,8 @@ pub const PROOF_SIZE: usize = 256;
 pub const MIN_PROOF_LENGTH: usize = 256;
 
 /// Verify a Groth16 proof for transfer using Alt_bn128 syscalls
-/// 
-/// In DEMO_MODE: Performs structural validation only (for testing without circuits)
-/// In PRODUCTION: Full pairing check using Solana syscalls
 pub fn verify_transfer_proof(
+    vk: &VerifyingKey,
     proof_bytes: &[u8],
     input_commitment: &[u8; 32],
     nullifier: &[u8; 32],
@@ -35,41 +46,52 @@ pub fn verify_transfer_proof(
 
     // 2. Construct public inputs array (matches circuit public signals)
     let public_inputs: [[u8; 32]; 5] = [
-        *input_commitment,
-        *nullifier,
-        *output_commitment,
-        *change_commitment,
-        *merkle_root,
+        to_field(input_commitment)?,
+        to_field(nullifier)?,
+        to_field(output_commitment)?,
+        to_field(change_commitment)?,
+        to_field(merkle_root)?,
     ];
 
-    // 3. In DEMO_MODE, skip pairing check but validate structure
-    if DEMO_MODE {
-        msg!("⚠️ DEMO MODE: Performing structural validation only");
-        return verify_proof_structure(proof_bytes, &public_inputs);
-    }
+    verify_with_key(vk, CircuitId::Transfer, proof_bytes, &public_inputs)
+}
 
-    // 4. PRODUCTION: Real Groth16 verification using Alt_bn128 syscalls
-    #[cfg(not(feature = "demo"))]
-    {
-        verify_groth16_proof(
-            proof_bytes,
-            &public_inputs,
-            &transfer_vk::ALPHA_G1,
-            &transfer_vk::BETA_G2,
-            &transfer_vk::GAMMA_G2,
-            &transfer_vk::DELTA_G2,
-            &transfer_vk::IC,
-        )
-    }
+/// Output and change commitments of a full withdrawal of `amount`
+///
+/// An unshield is a transfer whose output is `Poseidon(amount, 0)` and whose
+/// change is `Poseidon(0, 0)`. The circuit's value conservation then forces
+/// the spent note's committed amount to be exactly `amount`.
+pub fn withdrawal_commitments(amount: u64) -> Result<([u8; 32], [u8; 32])> {
+    Ok((
+        create_commitment(amount, &[0u8; 32])?,
+        create_commitment(0, &[0u8; 32])?,
+    ))
+}
 
-    #[cfg(feature = "demo")]
-    {
-        verify_proof_structure(proof_bytes, &public_inputs)
-    }
+/// Verify a withdraw proof: the transfer circuit paying `amount` out of the pool
+pub fn verify_withdraw_proof(
+    vk: &VerifyingKey,
+    proof_bytes: &[u8],
+    input_commitment: &[u8; 32],
+    nullifier: &[u8; 32],
+    amount: u64,
+    merkle_root: &[u8; 32],
+) -> Result<bool> {
+    let (output_commitment, change_commitment) = withdrawal_commitments(amount)?;
+    verify_transfer_proof(
+        vk,
+        proof_bytes,
+        input_commitment,
+        nullifier,
+        &output_commitment,
+        &change_commitment,
+        merkle_root,
+    )
 }
 
 /// Verify a shield proof (deposit)
 pub fn verify_shield_proof(
+    vk: &VerifyingKey,
     proof_bytes: &[u8],
     amount: u64,
     commitment: &[u8; 32],
@@ -79,41 +101,19 @@ pub fn verify_shield_proof(
         return Ok(false);
     }
 
-    // Convert amount to field element format
-    let mut amount_bytes = [0u8; 32];
-    amount_bytes[..8].copy_from_slice(&amount.to_le_bytes());
-
     let public_inputs: [[u8; 32]; 2] = [
-        amount_bytes,
-        *commitment,
+        u64_to_field(amount),
+        to_field(commitment)?,
     ];
 
-    if DEMO_MODE {
-        msg!("⚠️ DEMO MODE: Shield proof structural validation");
-        return verify_proof_structure(proof_bytes, &public_inputs);
-    }
-
-    #[cfg(not(feature = "demo"))]
-    {
-        verify_groth16_proof(
-            proof_bytes,
-            &public_inputs,
-            &shield_vk::ALPHA_G1,
-            &shield_vk::BETA_G2,
-            &shield_vk::GAMMA_G2,
-            &shield_vk::DELTA_G2,
-            &shield_vk::IC,
-        )
-    }
-
-    #[cfg(feature = "demo")]
-    {
-        verify_proof_structure(proof_bytes, &public_inputs)
-    }
+    verify_with_key(vk, CircuitId::Shield, proof_bytes, &public_inputs)
 }
 
 /// Verify range proof for selective disclosure
+///
+/// Public signals of `range.circom`: [commitment, minValue, maxValue].
 pub fn verify_range_proof(
+    vk: &VerifyingKey,
     proof_bytes: &[u8],
     commitment: &[u8; 32],
     min_value: u64,
@@ -129,19 +129,70 @@ pub fn verify_range_proof(
         return Ok(false);
     }
 
-    // For now, range proofs use structural validation
-    // Full implementation would use Bulletproofs or similar
-    let mut min_bytes = [0u8; 32];
-    let mut max_bytes = [0u8; 32];
-    min_bytes[..8].copy_from_slice(&min_value.to_le_bytes());
-    max_bytes[..8].copy_from_slice(&max_value.to_le_bytes());
+    let public_inputs: [[u8; 32]; 3] = [
+        to_field(commitment)?,
+        u64_to_field(min_value),
+        u64_to_field(max_value),
+    ];
+
+    verify_with_key(vk, CircuitId::Range, proof_bytes, &public_inputs)
+}
+
+/// Verify an ownership proof for selective disclosure
+///
+/// Public signals of `ownership.circom`: [ownerCommitment, vaultAddress].
+pub fn verify_ownership_proof(
+    vk: &VerifyingKey,
+    proof_bytes: &[u8],
+    owner_commitment: &[u8; 32],
+    vault: &Pubkey,
+) -> Result<bool> {
+    if proof_bytes.len() < MIN_PROOF_LENGTH {
+        msg!("ERROR: Ownership proof too short");
+        return Ok(false);
+    }
+
+    let public_inputs: [[u8; 32]; 2] = [
+        to_field(owner_commitment)?,
+        pubkey_to_field(vault),
+    ];
 
-    let public_inputs: [[u8; 32]; 3] = [*commitment, min_bytes, max_bytes];
-    verify_proof_structure(proof_bytes, &public_inputs)
+    verify_with_key(vk, CircuitId::Ownership, proof_bytes, &public_inputs)
 }
 
 // ============ Core Verification Functions ============
 
+/// Check `vk` is the active key for `circuit`, then verify the proof against it
+///
+/// Full pairing check, or the structural check in `demo` builds.
+fn verify_with_key<const N: usize>(
+    vk: &VerifyingKey,
+    circuit: CircuitId,
+    proof_bytes: &[u8],
+    public_inputs: &[[u8; 32]; N],
+) -> Result<bool> {
+    vk.check_active(circuit)?;
+
+    #[cfg(not(feature = "demo"))]
+    {
+        verify_groth16_proof(
+            proof_bytes,
+            public_inputs,
+            &vk.alpha_g1,
+            &vk.beta_g2,
+            &vk.gamma_g2,
+            &vk.delta_g2,
+            &vk.ic,
+        )
+    }
+
+    #[cfg(feature = "demo")]
+    {
+        msg!("⚠️ DEMO: {:?} proof structural validation only", circuit);
+        verify_proof_structure(proof_bytes, public_inputs)
+    }
+}
+
 /// Real Groth16 verification using Solana's Alt_bn128 syscalls
 /// 
 /// This performs the pairing check: e(A, B) = e(α, β) · e(L, γ) · e(C, δ)
@@ -315,6 +366,7 @@ fn alt_bn128_pairing(input: &[u8]) -> Result<bool> {
 /// 1. Proof has correct length
 /// 2. Public inputs are non-zero
 /// 3. Proof binds to public inputs (via hash check)
+#[cfg(any(feature = "demo", test))]
 fn verify_proof_structure<const N: usize>(
     proof_bytes: &[u8],
     public_inputs: &[[u8; 32]; N],
@@ -331,32 +383,91 @@ fn verify_proof_structure<const N: usize>(
         }
     }
 
-    // Compute binding hash: H(proof || public_inputs)
-    let mut binding_data = Vec::with_capacity(proof_bytes.len() + N * 32);
-    binding_data.extend_from_slice(proof_bytes);
-    for input in public_inputs.iter() {
-        binding_data.extend_from_slice(input);
-    }
-    
-    let binding_hash = anchor_lang::solana_program::keccak::hash(&binding_data);
-    
-    // Check that proof contains the binding (first 8 bytes of hash in last 8 bytes of proof)
-    // This is a structural check, not cryptographic proof
-    let proof_binding = &proof_bytes[proof_bytes.len() - 8..];
-    let expected_binding = &binding_hash.to_bytes()[..8];
-    
-    if proof_binding != expected_binding {
-        msg!("DEMO: Proof binding mismatch (expected in production this would fail)");
-        // In demo mode, we still pass to allow testing
+    // The last 8 bytes of the proof must be the first 8 bytes of
+    // H(proof without binding || public_inputs). This is a structural check,
+    // not a cryptographic proof, but a tampered proof or input is rejected.
+    let (body, proof_binding) = proof_bytes.split_at(proof_bytes.len() - 8);
+    if proof_binding != proof_binding_hash(body, public_inputs) {
+        msg!("❌ DEMO: Proof binding mismatch");
+        return Ok(false);
     }
 
     msg!("✅ DEMO: Proof structure validated");
     Ok(true)
 }
 
+/// Binding a demo proof carries: the first 8 bytes of H(body || public_inputs)
+#[cfg(any(feature = "demo", test))]
+fn proof_binding_hash<const N: usize>(body: &[u8], public_inputs: &[[u8; 32]; N]) -> [u8; 8] {
+    let mut binding_data = Vec::with_capacity(body.len() + N * 32);
+    binding_data.extend_from_slice(body);
+    for input in public_inputs.iter() {
+        binding_data.extend_from_slice(input);
+    }
+
+    let binding_hash = anchor_lang::solana_program::keccak::hash(&binding_data);
+    let mut binding = [0u8; 8];
+    binding.copy_from_slice(&binding_hash.to_bytes()[..8]);
+    binding
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::errors::AshbornError;
+    use crate::state::VerifyingKeyStatus;
+    use crate::zk::vkeys_generated::{range_vk, shield_vk, transfer_vk};
+
+    /// Active on-chain key built from the generated VK module for `circuit`
+    fn active_key(circuit: CircuitId) -> VerifyingKey {
+        let (alpha_g1, beta_g2, gamma_g2, delta_g2, ic) = match circuit {
+            CircuitId::Shield => (shield_vk::ALPHA_G1, shield_vk::BETA_G2, shield_vk::GAMMA_G2, shield_vk::DELTA_G2, shield_vk::IC.to_vec()),
+            CircuitId::Transfer => (transfer_vk::ALPHA_G1, transfer_vk::BETA_G2, transfer_vk::GAMMA_G2, transfer_vk::DELTA_G2, transfer_vk::IC.to_vec()),
+            CircuitId::Range => (range_vk::ALPHA_G1, range_vk::BETA_G2, range_vk::GAMMA_G2, range_vk::DELTA_G2, range_vk::IC.to_vec()),
+            // None is committed until circuits/setup_ownership.sh has been run
+            CircuitId::Ownership => panic!("no generated ownership key"),
+        };
+        VerifyingKey {
+            layout_version: VerifyingKey::CURRENT_LAYOUT_VERSION,
+            circuit,
+            version: 1,
+            status: VerifyingKeyStatus::Active,
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            bump: 255,
+            ic,
+        }
+    }
+
+    /// `range.circom` proof (snarkjs, range_0000.zkey) for value = 500,
+    /// blinding = 12345, minValue = 100, maxValue = 1000
+    const RANGE_PROOF: &str = "\
+        17845396e1a139ae0fbf88533413e735aedb01492690ad9e4884c180ca6255c9\
+        1ff41ae2430b87d6637c2b751bd6f4fd39eae8d86c9a8c7ef82eba49d1fe3de1\
+        2c89a8cc4363c4840a789146085f28c2e7bf824fa1cab3dba1a64b4ec9ffa990\
+        110e3043b29aa80fce3d96d8a61bfc4947474fc0afc2e6cff8d630c07f808f80\
+        117429afabfb547b52173c85fb7fc44e276a874c9df957194fd68db49c7d7ac0\
+        0a878ca1aa1f237942b61b416cf91e8ebbb4c09d03ab9c4e4dd35cc44f1f442a\
+        172f5c9482c1a16b285b8eed6e6e5035b63887c29a4dfd14aa85f58b92752e7f\
+        07f813cc8d206e0344f41148b16cc61418db8b5aa7268aee41757ca54950226c";
+
+    /// Poseidon(500, 12345)
+    const RANGE_COMMITMENT: &str =
+        "05d3874161a73290ec1a4575b81884ba9b622dc8c78125a8b1432cb331fc94d3";
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn range_fixture() -> (Vec<u8>, [u8; 32]) {
+        let commitment: [u8; 32] = unhex(RANGE_COMMITMENT).try_into().unwrap();
+        (unhex(RANGE_PROOF), commitment)
+    }
 
     #[test]
     fn test_proof_length_validation() {
@@ -368,6 +479,7 @@ mod tests {
         let root = [5u8; 32];
 
         let result = verify_transfer_proof(
+            &active_key(CircuitId::Transfer),
             &short_proof,
             &commitment,
             &nullifier,
@@ -378,4 +490,79 @@ mod tests {
         assert!(result.is_ok());
         assert!(!result.unwrap()); // Should fail due to short proof
     }
+
+    #[test]
+    fn test_withdrawal_commitments_bind_amount() {
+        let (output, change) = withdrawal_commitments(1_000).unwrap();
+        let (other_output, other_change) = withdrawal_commitments(1_001).unwrap();
+
+        // A proof for one amount can't be replayed for another
+        assert_ne!(output, other_output);
+        assert_eq!(change, other_change);
+        assert_eq!(output, create_commitment(1_000, &[0u8; 32]).unwrap());
+        assert_eq!(change, create_commitment(0, &[0u8; 32]).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_public_input() {
+        let proof = vec![0u8; PROOF_SIZE];
+        let err = verify_shield_proof(&active_key(CircuitId::Shield), &proof, 1, &[0xff; 32]).unwrap_err();
+        assert_eq!(err, AshbornError::NonCanonicalFieldElement.into());
+    }
+
+    #[test]
+    #[cfg(not(feature = "demo"))]
+    fn test_range_proof_verifies() {
+        let (proof, commitment) = range_fixture();
+        let vk = active_key(CircuitId::Range);
+        assert!(verify_range_proof(&vk, &proof, &commitment, 100, 1000).unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "demo"))]
+    fn test_range_proof_rejects_value_outside_range() {
+        let (proof, commitment) = range_fixture();
+        let vk = active_key(CircuitId::Range);
+        // The committed value (500) is below the claimed minimum / above the claimed maximum
+        assert!(!verify_range_proof(&vk, &proof, &commitment, 600, 1000).unwrap());
+        assert!(!verify_range_proof(&vk, &proof, &commitment, 100, 400).unwrap());
+        // A different commitment doesn't reuse the proof either
+        assert!(!verify_range_proof(&vk, &proof, &[1u8; 32], 100, 1000).unwrap());
+    }
+
+    #[test]
+    fn test_only_the_circuits_active_key_is_used() {
+        let (proof, commitment) = range_fixture();
+
+        let vk = active_key(CircuitId::Shield);
+        let err = verify_range_proof(&vk, &proof, &commitment, 100, 1000).unwrap_err();
+        assert_eq!(err, AshbornError::InvalidVerifyingKey.into());
+
+        let mut vk = active_key(CircuitId::Range);
+        vk.status = VerifyingKeyStatus::Retired;
+        let err = verify_range_proof(&vk, &proof, &commitment, 100, 1000).unwrap_err();
+        assert_eq!(err, AshbornError::InactiveVerifyingKey.into());
+
+        let mut vk = active_key(CircuitId::Range);
+        vk.ic.pop();
+        let err = verify_range_proof(&vk, &proof, &commitment, 100, 1000).unwrap_err();
+        assert_eq!(err, AshbornError::InvalidVerifyingKey.into());
+    }
+
+    #[test]
+    fn test_demo_structure_check_enforces_binding() {
+        let public_inputs = [u64_to_field(1_000), [7u8; 32]];
+        let mut proof = vec![3u8; PROOF_SIZE];
+        let binding = proof_binding_hash(&proof[..PROOF_SIZE - 8], &public_inputs);
+        proof[PROOF_SIZE - 8..].copy_from_slice(&binding);
+        assert!(verify_proof_structure(&proof, &public_inputs).unwrap());
+
+        // Different public inputs
+        let other_inputs = [u64_to_field(1_001), [7u8; 32]];
+        assert!(!verify_proof_structure(&proof, &other_inputs).unwrap());
+
+        // Tampered proof body
+        proof[0] ^= 1;
+        assert!(!verify_proof_structure(&proof, &public_inputs).unwrap());
+    }
 }
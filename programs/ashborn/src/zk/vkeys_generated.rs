@@ -3,8 +3,6 @@
 //! Generated from Circom circuits using snarkjs
 //! DO NOT EDIT - regenerate using convert_vk.js
 
-pub const DEMO_MODE: bool = false; // Real VKs!
-
 /// Verification key for shield circuit
 /// Generated from shield_vkey.json
 pub mod shield_vk {
@@ -13,6 +13,8 @@ import {
   Keypair,
 } from "@solana/web3.js";
 import { AnchorProvider, Wallet, BN } from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { keccak_256 } from "@noble/hashes/sha3";
 import { ShadowWire } from "./shadowwire";
 import { PrivacyCash } from "./privacycash";
 import { RangeCompliance } from "./compliance";
@@ -26,7 +28,7 @@ import {
   ShieldedNote,
   ProofType,
 } from "./types";
-import { PROGRAM_ID, SEEDS } from "./constants";
+import { DEMO_PROGRAM_ID, PROGRAM_ID, SEEDS } from "./constants";
 
 /**
  * Main Ashborn SDK class
@@ -169,11 +171,22 @@ export class Ashborn {
       blindingFactor ?? Keypair.generate().secretKey.slice(0, 32),
     );
 
+    // The demo build only checks a proof's structure; every other
+    // deployment verifies a real shield circuit proof
+    let proof = params.proof;
+    if (!proof) {
+      if (!this.programId.equals(DEMO_PROGRAM_ID)) {
+        throw new Error("A shield circuit proof is required");
+      }
+      proof = this.createDemoShieldProof(amount, commitment);
+    }
+
+    // The program numbers notes from 1: the next note is note_count + 1
     const vaultAddress = this.getVaultAddress();
     const vault = await this.getVault();
     const noteAddress = this.getNoteAddress(
       vaultAddress,
-      vault?.noteCount ?? 0,
+      (vault?.noteCount ?? 0) + 1,
     );
 
     const tx = new Transaction().add(
@@ -182,6 +195,7 @@ export class Ashborn {
         noteAddress,
         amount,
         commitment,
+        proof,
         mint,
       ),
     );
@@ -407,29 +421,110 @@ export class Ashborn {
     noteAddress: PublicKey,
     amount: bigint,
     commitment: Uint8Array,
-    _mint: PublicKey,
+    proof: Uint8Array,
+    mint: PublicKey,
   ): Promise<TransactionInstruction> {
-    // Discriminator for simplified 'shield' instruction (sha256("global:shield")[0..8])
-    const discriminator = Buffer.from([220, 198, 253, 246, 231, 84, 147, 98]);
+    // Discriminator for 'shield_deposit' (sha256("global:shield_deposit")[0..8])
+    const discriminator = Buffer.from([197, 58, 196, 180, 224, 142, 178, 40]);
 
     const data = Buffer.concat([
       discriminator,
       new BN(amount.toString()).toArrayLike(Buffer, "le", 8),
       Buffer.from(commitment),
+      new BN(proof.length).toArrayLike(Buffer, "le", 4),
+      Buffer.from(proof),
     ]);
 
+    const pda = (...seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(seeds, this.programId)[0];
+
+    // Active commitment tree shard and the shard a rollover would create
+    const poolConfig = pda(Buffer.from("pool_config"), mint.toBuffer());
+    const poolConfigInfo = await this.connection.getAccountInfo(poolConfig);
+    if (!poolConfigInfo) {
+      throw new Error(`No shielded pool for mint ${mint.toBase58()}`);
+    }
+    const commitmentTree = new PublicKey(poolConfigInfo.data.subarray(58, 90));
+    const treeInfo = await this.connection.getAccountInfo(commitmentTree);
+    if (!treeInfo) {
+      throw new Error("Commitment tree not found");
+    }
+    const treeId = new BN(treeInfo.data.subarray(1328, 1336), "le");
+    const nextCommitmentTree = pda(
+      Buffer.from(SEEDS.COMMITMENT_TREE),
+      mint.toBuffer(),
+      treeId.addn(1).toArrayLike(Buffer, "le", 8),
+    );
+
+    // Active shield verifying key, from the key registry
+    const registryInfo = await this.connection.getAccountInfo(
+      pda(Buffer.from("vk_registry")),
+    );
+    const keyVersion = registryInfo?.data.readUInt32LE(9) ?? 0;
+    if (keyVersion === 0) {
+      throw new Error("No active shield verifying key");
+    }
+    const verifyingKey = pda(
+      Buffer.from("verifying_key"),
+      Buffer.from([0]), // CircuitId::Shield
+      new BN(keyVersion).toArrayLike(Buffer, "le", 4),
+    );
+
     return new TransactionInstruction({
       keys: [
-        { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
         { pubkey: vaultAddress, isSigner: false, isWritable: true },
+        {
+          pubkey: getAssociatedTokenAddressSync(mint, this.wallet.publicKey),
+          isSigner: false,
+          isWritable: true,
+        },
+        { pubkey: mint, isSigner: false, isWritable: false },
+        { pubkey: pda(Buffer.from("shielded_pool"), mint.toBuffer()), isSigner: false, isWritable: true },
+        { pubkey: poolConfig, isSigner: false, isWritable: true },
+        { pubkey: pda(Buffer.from(SEEDS.POOL_AUTHORITY), mint.toBuffer()), isSigner: false, isWritable: false },
+        { pubkey: pda(Buffer.from(SEEDS.PROTOCOL_STATE)), isSigner: false, isWritable: true },
+        { pubkey: verifyingKey, isSigner: false, isWritable: false },
+        { pubkey: commitmentTree, isSigner: false, isWritable: true },
+        { pubkey: nextCommitmentTree, isSigner: false, isWritable: true },
+        { pubkey: pda(Buffer.from(SEEDS.RATE_LIMIT)), isSigner: false, isWritable: true },
         { pubkey: noteAddress, isSigner: false, isWritable: true },
+        { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        // #[event_cpi] accounts
+        { pubkey: pda(Buffer.from("__event_authority")), isSigner: false, isWritable: false },
+        { pubkey: this.programId, isSigner: false, isWritable: false },
       ],
       programId: this.programId,
       data,
     });
   }
 
+  /**
+   * Proof the demo build accepts: any 248-byte body followed by the first
+   * 8 bytes of keccak256(body || amount || commitment), with the public
+   * inputs as 32-byte big-endian field elements
+   */
+  private createDemoShieldProof(
+    amount: bigint,
+    commitment: Uint8Array,
+  ): Uint8Array {
+    const proofBody = new Uint8Array(248);
+
+    const binding = keccak_256(
+      Buffer.concat([
+        proofBody,
+        new BN(amount.toString()).toArrayLike(Buffer, "be", 32),
+        Buffer.from(commitment),
+      ]),
+    );
+
+    const proof = new Uint8Array(256);
+    proof.set(proofBody);
+    proof.set(binding.slice(0, 8), 248);
+    return proof;
+  }
+
   private async createTransferInstruction(
     vaultAddress: PublicKey,
     sourceNoteAddress: PublicKey,
@@ -11,6 +11,14 @@ export const PROGRAM_ID = new PublicKey(
   "BzBUgtEFiJjUXR2xjsvhvVx2oZEhD2K6qenpg727z5Qe", // Ashborn Devnet Program ID
 );
 
+/**
+ * Program ID of `demo` builds (structural proof checks, devnet only).
+ * Pass it as the program ID when talking to the demo deployment.
+ */
+export const DEMO_PROGRAM_ID = new PublicKey(
+  "AMJ7hCChY9hQrpi2Wr9gyiLm1AF6qSJwnwyFbCwYFd8t",
+);
+
 /**
  * PDA seeds for account derivation
  */
@@ -21,6 +29,8 @@ export const SEEDS = {
   COMPLIANCE_PROOF: "compliance_proof",
   POOL_AUTHORITY: "pool_authority",
   PROTOCOL_STATE: "protocol_state",
+  COMMITMENT_TREE: "commitment_tree",
+  RATE_LIMIT: "rate_limit",
 } as const;
 
 /**
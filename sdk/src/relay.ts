@@ -321,7 +321,7 @@ export class PrivacyRelay {
         // STEP 1: Register with Ashborn Program (commitment + Merkle tree)
         try {
             const { Ashborn } = await import("./ashborn");
-            const { PROGRAM_ID } = await import("./constants");
+            const { DEMO_PROGRAM_ID } = await import("./constants");
 
             // Create wallet interface for Ashborn class
             const wallet = {
@@ -336,7 +336,8 @@ export class PrivacyRelay {
                 },
             };
 
-            const ashborn = new Ashborn(this.connection, wallet as any, { programId: PROGRAM_ID });
+            // The relay has no shield circuit prover, so it targets the demo build
+            const ashborn = new Ashborn(this.connection, wallet as any, { programId: DEMO_PROGRAM_ID });
 
             // Try to initialize vault (will fail if already exists, that's OK)
             try {
@@ -41,6 +41,8 @@ export interface ShieldParams {
   mint: PublicKey;
   /** Optional blinding factor (generated if not provided) */
   blindingFactor?: Uint8Array;
+  /** Shield circuit proof (built for the demo program if not provided) */
+  proof?: Uint8Array;
 }
 
 /**
@@ -104,6 +106,7 @@ export interface ShieldedNote {
   commitment: Uint8Array;
   encryptedAmount: Uint8Array;
   index: number;
+  leafIndex: BN;
   spent: boolean;
   createdAt: BN;
   bump: number;